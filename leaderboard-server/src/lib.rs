@@ -0,0 +1,689 @@
+//! The boards behind the leaderboard server: one per game mode, checked on
+//! the way in and kept as JSON files in a data directory. With a signing
+//! key set, only scores signed with it are taken, and each nonce only once.
+//! A submission that arrives again with the nonce it was taken with is
+//! answered as taken, so a client retrying after a lost answer isn't
+//! turned away.
+//! Names go through the same [`Moderator`] the game uses. Player IDs are
+//! kept but never served, since holding one is what lets a player rename
+//! their entry.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use snake_core::game_mode::GameRules;
+use snake_core::moderation::{Moderator, NameProblem};
+use snake_core::replay::ReplayError;
+use snake_core::score::{
+    insert_best, BoardPage, BoardQuery, Highscore, Highscores, Standing,
+};
+pub use snake_core::signing::SIGNATURE_WINDOW;
+use snake_core::signing::{self, Signature};
+use snake_core::sim::BOARD_CELLS;
+
+/// Mode used when a request doesn't name one
+pub const DEFAULT_MODE: &str = "classic";
+
+/// Entries kept per board, lower scores are dropped
+pub const BOARD_LIMIT: usize = 1000;
+
+/// No run can score more than there are cells on the board
+pub const MAX_SCORE: u32 = (BOARD_CELLS * BOARD_CELLS) as u32;
+
+const NONCES_FILE: &str = "nonces.json";
+
+/// Why a request was turned away
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    UnknownMode(String),
+    /// The name breaks the naming rules or is blocked
+    Name(NameProblem),
+    ScoreTooHigh(u32),
+    /// The run was played on a board of another size
+    WrongBoardSize(i32),
+    MissingReplay,
+    /// The replay was played under another mode's rules
+    WrongMode(String),
+    /// Playing the replay back doesn't give the submitted score
+    Replay(ReplayError),
+    MissingSignature,
+    BadSignature,
+    /// The signature's timestamp is outside [`SIGNATURE_WINDOW`]
+    StaleSignature,
+    /// A different submission was already taken with this nonce
+    ReusedNonce,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::UnknownMode(mode) => {
+                write!(f, "unknown mode {:?}", mode)
+            }
+            Rejection::Name(problem) => write!(f, "{}", problem),
+            Rejection::ScoreTooHigh(score) => {
+                write!(f, "score {} is not possible", score)
+            }
+            Rejection::WrongBoardSize(size) => {
+                write!(
+                    f,
+                    "the board must be {0}x{0}, not {1}x{1}",
+                    BOARD_CELLS, size
+                )
+            }
+            Rejection::MissingReplay => {
+                write!(f, "scores must come with a replay of the run")
+            }
+            Rejection::WrongMode(mode) => {
+                write!(f, "the run was played in {} mode", mode)
+            }
+            Rejection::MissingSignature => write!(f, "scores must be signed"),
+            Rejection::BadSignature => write!(f, "the signature doesn't match"),
+            Rejection::StaleSignature => {
+                write!(f, "the signature has expired, check the clock")
+            }
+            Rejection::ReusedNonce => {
+                write!(f, "the signature's nonce was already used")
+            }
+            Rejection::Replay(e) => {
+                write!(f, "replay doesn't check out, {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// Checks that `mode` is one the game can be played in
+pub fn check_mode(mode: &str) -> Result<(), Rejection> {
+    match GameRules::from_name(mode) {
+        Some(_) => Ok(()),
+        None => Err(Rejection::UnknownMode(mode.to_string())),
+    }
+}
+
+/// Checks a submission for `mode` against what the game itself allows,
+/// the name against `moderator`, then plays its replay back to make sure
+/// the run scores what it claims
+pub fn validate(
+    mode: &str,
+    highscore: &Highscore,
+    moderator: &Moderator,
+) -> Result<(), Rejection> {
+    moderator.check(&highscore.name).map_err(Rejection::Name)?;
+    if highscore.score > MAX_SCORE {
+        return Err(Rejection::ScoreTooHigh(highscore.score));
+    }
+    if highscore.board_size != BOARD_CELLS {
+        return Err(Rejection::WrongBoardSize(highscore.board_size));
+    }
+    if highscore.mode() != mode {
+        return Err(Rejection::WrongMode(highscore.mode().to_string()));
+    }
+
+    let replay = highscore.replay.as_ref().ok_or(Rejection::MissingReplay)?;
+    if replay.rules != highscore.rules {
+        return Err(Rejection::WrongMode(replay.rules.name().to_string()));
+    }
+    replay.verify(highscore.score).map_err(Rejection::Replay)
+}
+
+/// Reads a query string like `a=1&b=%C3%A9+c`, percent-decoding keys and
+/// values and taking `+` for a space as HTML forms send it. The first of a
+/// repeated key wins. Broken escapes and bytes that aren't UTF-8 are an
+/// error.
+pub fn parse_query(query: &str) -> Result<HashMap<String, String>, String> {
+    let mut params = HashMap::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        params
+            .entry(percent_decode(key)?)
+            .or_insert(percent_decode(value)?);
+    }
+    Ok(params)
+}
+
+fn percent_decode(encoded: &str) -> Result<String, String> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = encoded
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("bad escape in {:?}", encoded))?;
+                decoded.push(byte);
+                i += 2;
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded)
+        .map_err(|_| format!("{:?} isn't UTF-8 once decoded", encoded))
+}
+
+/// Every board the server knows about. Boards are read from disk the first
+/// time they are asked for and written back after every submission.
+#[derive(Debug, Default)]
+pub struct Store {
+    /// Where boards are kept, `None` keeps them in memory only
+    dir: Option<PathBuf>,
+    boards: HashMap<String, Highscores>,
+    /// Submissions have to be signed with this when set
+    key: Option<Vec<u8>>,
+    /// Nonces of submissions taken within the signature window, with their
+    /// timestamps and MACs
+    nonces: HashMap<u64, (u64, String)>,
+    moderator: Moderator,
+}
+
+impl Store {
+    pub fn new(dir: Option<PathBuf>) -> std::io::Result<Self> {
+        if let Some(dir) = &dir {
+            std::fs::create_dir_all(dir)?;
+        }
+        let nonces = dir
+            .as_ref()
+            .and_then(|dir| std::fs::read_to_string(dir.join(NONCES_FILE)).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            dir,
+            boards: HashMap::new(),
+            key: None,
+            nonces,
+            moderator: Moderator::builtin(),
+        })
+    }
+
+    pub fn with_key(mut self, key: Option<Vec<u8>>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_moderator(mut self, moderator: Moderator) -> Self {
+        self.moderator = moderator;
+        self
+    }
+
+    /// Checks the signature, if the store has a key. Returns `true` when
+    /// this very submission was taken before.
+    fn check_signature(
+        &mut self,
+        mode: &str,
+        highscore: &Highscore,
+    ) -> Result<bool, Rejection> {
+        let Some(key) = &self.key else {
+            return Ok(false);
+        };
+        let signature: &Signature = highscore
+            .signature
+            .as_ref()
+            .ok_or(Rejection::MissingSignature)?;
+        if !signing::verify(key, highscore, mode, signature) {
+            return Err(Rejection::BadSignature);
+        }
+        let now = signing::now();
+        if now.abs_diff(signature.timestamp) > SIGNATURE_WINDOW {
+            return Err(Rejection::StaleSignature);
+        }
+
+        self.nonces
+            .retain(|_, (at, _)| now.abs_diff(*at) <= SIGNATURE_WINDOW);
+        match self.nonces.get(&signature.nonce) {
+            Some((_, mac)) if *mac == signature.mac => Ok(true),
+            Some(_) => Err(Rejection::ReusedNonce),
+            None => Ok(false),
+        }
+    }
+
+    /// Remembers the nonce of a submission that was taken
+    fn use_nonce(&mut self, signature: &Signature) {
+        self.nonces.insert(
+            signature.nonce,
+            (signature.timestamp, signature.mac.clone()),
+        );
+        if let Some(dir) = &self.dir {
+            write_json(&dir.join(NONCES_FILE), &self.nonces);
+        }
+    }
+
+    fn path(&self, mode: &str) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", mode)))
+    }
+
+    fn board(&mut self, mode: &str) -> &mut Highscores {
+        if !self.boards.contains_key(mode) {
+            let mut board: Highscores = self
+                .path(mode)
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .and_then(|text| serde_json::from_str(&text).ok())
+                .unwrap_or_default();
+            board.highscores.sort_by_key(|s| std::cmp::Reverse(s.score));
+            self.boards.insert(mode.to_string(), board);
+        }
+        self.boards.get_mut(mode).unwrap()
+    }
+
+    /// One page of a board, filtered by `query`
+    pub fn page(&mut self, query: &BoardQuery) -> Result<BoardPage, Rejection> {
+        check_mode(&query.mode)?;
+        let board = &self.board(&query.mode).highscores;
+        let mut page = query.apply(board, signing::now());
+        hide_players(page.entries.iter_mut().map(|e| &mut e.highscore));
+        Ok(page)
+    }
+
+    /// Where `score` would place on a mode's board, with up to `around`
+    /// entries either side
+    pub fn standing(
+        &mut self,
+        mode: &str,
+        score: u32,
+        around: usize,
+    ) -> Result<Standing, Rejection> {
+        check_mode(mode)?;
+        let mut standing =
+            Standing::on(&self.board(mode).highscores, score, around);
+        hide_players(standing.above.iter_mut().chain(&mut standing.below));
+        Ok(standing)
+    }
+
+    /// The best `limit` scores of a mode, best first
+    pub fn top(
+        &mut self,
+        mode: &str,
+        limit: usize,
+    ) -> Result<Highscores, Rejection> {
+        check_mode(mode)?;
+        let mut highscores: Vec<_> = self
+            .board(mode)
+            .highscores
+            .iter()
+            .take(limit)
+            .cloned()
+            .collect();
+        hide_players(&mut highscores);
+        Ok(Highscores { highscores })
+    }
+
+    /// Adds a score to a mode's board and saves it. Replays are only kept
+    /// long enough to check the score.
+    pub fn submit(
+        &mut self,
+        mode: &str,
+        mut highscore: Highscore,
+    ) -> Result<(), Rejection> {
+        check_mode(mode)?;
+        if self.check_signature(mode, &highscore)? {
+            return Ok(());
+        }
+        validate(mode, &highscore, &self.moderator)?;
+        let signature = highscore.signature.as_ref();
+        if let Some(signature) = signature.filter(|_| self.key.is_some()) {
+            self.use_nonce(signature);
+        }
+        // the replay, not the player, says how the run went
+        highscore.stats =
+            highscore.replay.as_ref().and_then(|r| r.stats().ok());
+        highscore.replay = None;
+        highscore.signature = None;
+        // the board's clock, not the player's, decides when a score was set
+        highscore.timestamp = Some(signing::now());
+        let board = self.board(mode);
+        insert_best(&mut board.highscores, highscore);
+        board.highscores.truncate(BOARD_LIMIT);
+        self.save(mode);
+        Ok(())
+    }
+
+    fn save(&self, mode: &str) {
+        if let (Some(path), Some(board)) =
+            (self.path(mode), self.boards.get(mode))
+        {
+            write_json(&path, board);
+        }
+    }
+}
+
+fn hide_players<'a>(scores: impl IntoIterator<Item = &'a mut Highscore>) {
+    for highscore in scores {
+        highscore.player_id = None;
+    }
+}
+
+/// Writes `value` next to `path` and moves it over, so a crash never leaves
+/// half a file behind
+fn write_json(path: &Path, value: &impl serde::Serialize) {
+    let tmp = path.with_extension("json.tmp");
+    let res = serde_json::to_string_pretty(value)
+        .map_err(std::io::Error::from)
+        .and_then(|json| std::fs::write(&tmp, json))
+        .and_then(|_| std::fs::rename(&tmp, path));
+    if let Err(e) = res {
+        eprintln!("couldn't save {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use snake_core::replay::Replay;
+    use snake_core::sim::Simulation;
+
+    use super::*;
+
+    /// A finished run that goes straight ahead from the start
+    fn run(name: &str, rules: GameRules, seed: u64) -> Highscore {
+        let mut sim = Simulation::new(rules, seed);
+        let mut replay = Replay::new(seed, rules);
+        while !sim.is_over() {
+            sim.step(None);
+            replay.record(None);
+        }
+        Highscore {
+            replay: Some(replay),
+            ..Highscore::new(name, sim.score(), rules)
+        }
+    }
+
+    fn classic(name: &str) -> Highscore {
+        run(name, GameRules::CLASSIC, 13)
+    }
+
+    fn validate_classic(highscore: &Highscore) -> Result<(), Rejection> {
+        validate("classic", highscore, &Moderator::builtin())
+    }
+
+    /// A data directory of its own for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "leaderboard-server-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn validate_accepts_an_honest_run() {
+        let highscore = classic("Alice");
+        assert_eq!(highscore.score, 1);
+        assert_eq!(validate_classic(&highscore), Ok(()));
+        let walls = run("Alice", GameRules::WALLS, 3);
+        assert_eq!(validate("walls", &walls, &Moderator::builtin()), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_bad_names() {
+        let mut highscore = classic("");
+        assert_eq!(
+            validate_classic(&highscore),
+            Err(Rejection::Name(NameProblem::Empty))
+        );
+        highscore.name = "Bad Name".to_string();
+        assert_eq!(
+            validate_classic(&highscore),
+            Err(Rejection::Name(NameProblem::BadCharacter(' ')))
+        );
+        highscore.name = "Banned".to_string();
+        let moderator = Moderator::from_list("banned");
+        assert_eq!(
+            validate("classic", &highscore, &moderator),
+            Err(Rejection::Name(NameProblem::Blocked))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_impossible_runs() {
+        let mut highscore = classic("Alice");
+        highscore.score = MAX_SCORE + 1;
+        assert_eq!(
+            validate_classic(&highscore),
+            Err(Rejection::ScoreTooHigh(MAX_SCORE + 1))
+        );
+
+        let mut highscore = classic("Alice");
+        highscore.board_size = 30;
+        assert_eq!(
+            validate_classic(&highscore),
+            Err(Rejection::WrongBoardSize(30))
+        );
+
+        let mut highscore = classic("Alice");
+        highscore.replay = None;
+        assert_eq!(validate_classic(&highscore), Err(Rejection::MissingReplay));
+
+        let mut highscore = classic("Alice");
+        highscore.score = 5;
+        assert_eq!(
+            validate_classic(&highscore),
+            Err(Rejection::Replay(ReplayError::ScoreMismatch {
+                claimed: 5,
+                replayed: 1,
+            }))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_runs_from_another_mode() {
+        let walls = run("Alice", GameRules::WALLS, 3);
+        assert_eq!(
+            validate_classic(&walls),
+            Err(Rejection::WrongMode("walls".to_string()))
+        );
+
+        // a classic score backed by a walls replay
+        let mut highscore = classic("Alice");
+        highscore.replay = walls.replay;
+        assert_eq!(
+            validate_classic(&highscore),
+            Err(Rejection::WrongMode("walls".to_string()))
+        );
+    }
+
+    #[test]
+    fn submit_keeps_what_the_board_needs() {
+        let mut store = Store::new(None).unwrap();
+        let mut highscore = classic("Alice");
+        highscore.timestamp = Some(0);
+        store.submit("classic", highscore).unwrap();
+
+        let top = store.top("classic", 10).unwrap().highscores;
+        assert_eq!(top.len(), 1);
+        let kept = &top[0];
+        assert_eq!(kept.name, "Alice");
+        assert!(kept.replay.is_none());
+        assert!(kept.timestamp.unwrap() > 0);
+        let stats = kept.stats.as_ref().unwrap();
+        assert_eq!(stats.length, 3);
+        assert!(stats.death.is_some());
+        assert!(store.top("walls", 10).unwrap().highscores.is_empty());
+    }
+
+    #[test]
+    fn unknown_modes_are_refused() {
+        let mut store = Store::new(None).unwrap();
+        let unknown = Err(Rejection::UnknownMode("maze".to_string()));
+        assert_eq!(store.submit("maze", classic("Alice")), unknown);
+        assert_eq!(store.top("maze", 10).map(|_| ()), unknown);
+        assert_eq!(store.standing("maze", 1, 2).map(|_| ()), unknown);
+        let query = BoardQuery {
+            mode: "maze".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(store.page(&query).map(|_| ()), unknown);
+    }
+
+    #[test]
+    fn boards_rank_and_page() {
+        let mut store = Store::new(None).unwrap();
+        // seed 13 scores 1, seeds 0 and 1 score nothing
+        store
+            .submit("classic", run("Zero", GameRules::CLASSIC, 0))
+            .unwrap();
+        store.submit("classic", classic("One")).unwrap();
+        store
+            .submit("classic", run("Nil", GameRules::CLASSIC, 1))
+            .unwrap();
+
+        let names = |board: &[Highscore]| {
+            board.iter().map(|s| s.name.clone()).collect::<Vec<_>>()
+        };
+        let top = store.top("classic", 10).unwrap().highscores;
+        assert_eq!(names(&top), ["One", "Zero", "Nil"]);
+
+        let standing = store.standing("classic", 1, 1).unwrap();
+        assert_eq!(standing.rank, 2);
+        assert_eq!(standing.total, 3);
+        assert_eq!(names(&standing.above), ["One"]);
+        assert_eq!(names(&standing.below), ["Zero"]);
+
+        let page = store
+            .page(&BoardQuery {
+                offset: 1,
+                limit: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].rank, 2);
+        assert_eq!(page.entries[0].highscore.name, "Zero");
+    }
+
+    #[test]
+    fn queries_are_percent_decoded() {
+        let params =
+            parse_query("mode=classic&name=%41l%69+c&name=Bob&within=")
+                .unwrap();
+        assert_eq!(params["mode"], "classic");
+        assert_eq!(params["name"], "Ali c");
+        assert_eq!(params["within"], "");
+        assert_eq!(parse_query("name=J%C3%B6rg").unwrap()["name"], "Jörg");
+        assert!(parse_query("").unwrap().is_empty());
+
+        assert!(parse_query("name=%4").is_err());
+        assert!(parse_query("name=%zz").is_err());
+        assert!(parse_query("name=%FF").is_err());
+    }
+
+    #[test]
+    fn encoded_name_searches_find_their_rows() {
+        let mut store = Store::new(None).unwrap();
+        store.submit("classic", classic("Alice")).unwrap();
+        store.submit("classic", classic("Bob")).unwrap();
+
+        // as `curl --data-urlencode name=lic` or a form would send it
+        let params = parse_query("mode=classic&name=%6Cic").unwrap();
+        let page = store
+            .page(&BoardQuery {
+                mode: params["mode"].clone(),
+                name: params["name"].clone(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].highscore.name, "Alice");
+    }
+
+    #[test]
+    fn player_ids_are_kept_but_never_served() {
+        let mut store = Store::new(None).unwrap();
+        let id = "alice".to_string();
+        let mut alice = classic("Alice");
+        alice.player_id = Some(id.clone());
+        store.submit("classic", alice).unwrap();
+
+        let top = store.top("classic", 10).unwrap().highscores;
+        assert!(top[0].player_id.is_none());
+        let standing = store.standing("classic", 0, 1).unwrap();
+        assert!(standing.above[0].player_id.is_none());
+        let page = store.page(&BoardQuery::default()).unwrap();
+        assert!(page.entries[0].highscore.player_id.is_none());
+
+        // holding the ID is what lets a worse run rename the entry
+        let mut renamed = run("Alicia", GameRules::CLASSIC, 0);
+        renamed.player_id = Some(id);
+        store.submit("classic", renamed).unwrap();
+        let top = store.top("classic", 10).unwrap().highscores;
+        assert_eq!(top.len(), 1);
+        assert_eq!((top[0].name.as_str(), top[0].score), ("Alicia", 1));
+    }
+
+    #[test]
+    fn boards_are_saved_and_read_back() {
+        let dir = temp_dir("saved");
+        let mut store = Store::new(Some(dir.clone())).unwrap();
+        store.submit("classic", classic("Alice")).unwrap();
+        drop(store);
+
+        let mut store = Store::new(Some(dir.clone())).unwrap();
+        let top = store.top("classic", 10).unwrap().highscores;
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].name, "Alice");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn signed_boards_check_signatures() {
+        let key = b"secret".to_vec();
+        let mut store = Store::new(None).unwrap().with_key(Some(key.clone()));
+
+        assert_eq!(
+            store.submit("classic", classic("Alice")),
+            Err(Rejection::MissingSignature)
+        );
+
+        let mut highscore = classic("Alice");
+        highscore.signature =
+            Some(signing::sign(b"other", &highscore, "classic"));
+        assert_eq!(
+            store.submit("classic", highscore),
+            Err(Rejection::BadSignature)
+        );
+
+        let mut highscore = classic("Alice");
+        highscore.signature = Some(signing::sign(&key, &highscore, "classic"));
+        highscore.name = "Mallory".to_string();
+        assert_eq!(
+            store.submit("classic", highscore),
+            Err(Rejection::BadSignature)
+        );
+
+        let mut highscore = classic("Alice");
+        highscore.signature = Some(signing::sign(&key, &highscore, "classic"));
+        assert_eq!(store.submit("classic", highscore.clone()), Ok(()));
+        // a retry of a submission that was taken is answered as taken
+        assert_eq!(store.submit("classic", highscore), Ok(()));
+        assert_eq!(store.top("classic", 10).unwrap().highscores.len(), 1);
+    }
+
+    #[test]
+    fn refused_submissions_keep_their_nonce_unused() {
+        let key = b"secret".to_vec();
+        let mut store = Store::new(None).unwrap().with_key(Some(key.clone()));
+
+        let mut highscore = classic("Alice");
+        highscore.score += 1;
+        highscore.signature = Some(signing::sign(&key, &highscore, "classic"));
+        assert!(matches!(
+            store.submit("classic", highscore.clone()),
+            Err(Rejection::Replay(_))
+        ));
+        // refused again for what it is, not taken as a repeat
+        assert!(matches!(
+            store.submit("classic", highscore),
+            Err(Rejection::Replay(_))
+        ));
+        assert!(store.top("classic", 10).unwrap().highscores.is_empty());
+    }
+}
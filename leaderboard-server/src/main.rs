@@ -0,0 +1,269 @@
+//! A self-hostable leaderboard for the game.
+//!
+//! `GET /api/snake-highscores` answers with the board as `{"highscores":
+//! [...]}`, best first, and `POST /api/snake-highscores` takes a single
+//! highscore as JSON, with a replay of the run that is played back to check
+//! the score. Both take `?mode=classic` or `?mode=walls`, and `GET`
+//! also takes `?limit=`. `GET /api/snake-highscores/rank?score=` answers
+//! with where a score places on the whole board and the entries around it,
+//! `?around=` of them either side. `GET /api/snake-highscores/page` pages
+//! through the board with `?offset=` and `?limit=`, keeping to scores set
+//! in the last `?within=` seconds and names containing `?name=` if given.
+//! Query values are percent-decoded, with `+` read as a space.
+//! `GET /snake-leaderboard` shows the board as a web page.
+//!
+//! `cargo run -p leaderboard-server -- --bind 0.0.0.0:8080 --data-dir scores`
+//! then start the game with `--leaderboard-url http://localhost:8080`.
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use clap::Parser;
+use leaderboard_server::{parse_query, Rejection, Store, DEFAULT_MODE};
+use snake_core::moderation::Moderator;
+use snake_core::score::{BoardQuery, Highscore, PAGE_SIZE};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Scores sent to `GET` when no limit is asked for
+const DEFAULT_LIMIT: usize = 100;
+
+/// Entries sent either side of a ranked score when no number is asked for
+const DEFAULT_AROUND: usize = 2;
+
+/// Most entries sent either side of a ranked score
+const MAX_AROUND: usize = 50;
+
+/// Most entries sent in one page
+const MAX_PAGE: usize = 100;
+
+/// Largest request body read, enough for a replay of a very long run
+const MAX_BODY: u64 = 1 << 20;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Address to listen on
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    bind: String,
+    /// Directory the boards are kept in, one JSON file per mode
+    #[clap(long, default_value = "leaderboard")]
+    data_dir: PathBuf,
+    /// Keep the boards in memory only, for tests and trying things out
+    #[clap(long)]
+    in_memory: bool,
+    /// Only take scores signed with this key
+    #[clap(long, env = snake_core::signing::KEY_ENV)]
+    signing_key: Option<String>,
+    /// Also refuse names containing the words in this file, one per line
+    #[clap(long, env = snake_core::moderation::BLOCKLIST_ENV)]
+    blocklist: Option<PathBuf>,
+}
+
+type Reply = Response<std::io::Cursor<Vec<u8>>>;
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header is valid")
+}
+
+/// Web builds call the API from another origin
+fn reply(status: u16, content_type: &str, body: String) -> Reply {
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", content_type))
+        .with_header(header("Access-Control-Allow-Origin", "*"))
+}
+
+fn text(status: u16, body: impl ToString) -> Reply {
+    reply(status, "text/plain; charset=utf-8", body.to_string())
+}
+
+fn rejected(rejection: Rejection) -> Reply {
+    let status = match rejection {
+        Rejection::UnknownMode(_) => 404,
+        _ => 422,
+    };
+    text(status, rejection)
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn leaderboard_page(store: &mut Store, mode: &str) -> Reply {
+    let board = match store.top(mode, DEFAULT_LIMIT) {
+        Ok(board) => board,
+        Err(rejection) => return rejected(rejection),
+    };
+    let rows: String = board
+        .highscores
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                i + 1,
+                html_escape(&s.name),
+                s.score
+            )
+        })
+        .collect();
+    let body = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <title>Snake leaderboard ({mode})</title></head><body>\n\
+         <h1>Snake leaderboard ({mode})</h1>\n\
+         <p><a href=\"?mode=classic\">classic</a> \
+         <a href=\"?mode=walls\">walls</a></p>\n\
+         <table><tr><th>#</th><th>Name</th><th>Score</th></tr>\n\
+         {rows}</table></body></html>\n",
+        mode = html_escape(mode),
+    );
+    reply(200, "text/html; charset=utf-8", body)
+}
+
+fn handle(store: &mut Store, request: &mut Request) -> Reply {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let params = match parse_query(query) {
+        Ok(params) => params,
+        Err(e) => return text(400, e),
+    };
+    let query_param = |key: &str| params.get(key).map(String::as_str);
+    let mode = query_param("mode").unwrap_or(DEFAULT_MODE);
+
+    match (request.method(), path) {
+        (Method::Get, "/api/snake-highscores") => {
+            let limit = query_param("limit")
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(DEFAULT_LIMIT);
+            match store.top(mode, limit) {
+                Ok(board) => reply(
+                    200,
+                    "application/json",
+                    serde_json::to_string(&board).expect("board serializes"),
+                ),
+                Err(rejection) => rejected(rejection),
+            }
+        }
+        (Method::Get, "/api/snake-highscores/rank") => {
+            let Some(score) = query_param("score").and_then(|s| s.parse().ok())
+            else {
+                return text(400, "score is missing");
+            };
+            let around = query_param("around")
+                .and_then(|a| a.parse().ok())
+                .unwrap_or(DEFAULT_AROUND)
+                .min(MAX_AROUND);
+            match store.standing(mode, score, around) {
+                Ok(standing) => reply(
+                    200,
+                    "application/json",
+                    serde_json::to_string(&standing)
+                        .expect("standing serializes"),
+                ),
+                Err(rejection) => rejected(rejection),
+            }
+        }
+        (Method::Get, "/api/snake-highscores/page") => {
+            let number = |key| query_param(key)?.parse().ok();
+            let page = BoardQuery {
+                mode: mode.to_string(),
+                offset: number("offset").unwrap_or(0),
+                limit: number("limit").unwrap_or(PAGE_SIZE).min(MAX_PAGE),
+                within: query_param("within").and_then(|w| w.parse().ok()),
+                name: query_param("name").unwrap_or("").to_string(),
+            };
+            match store.page(&page) {
+                Ok(page) => reply(
+                    200,
+                    "application/json",
+                    serde_json::to_string(&page).expect("page serializes"),
+                ),
+                Err(rejection) => rejected(rejection),
+            }
+        }
+        (Method::Post, "/api/snake-highscores") => {
+            let mut body = String::new();
+            let read = request
+                .as_reader()
+                .take(MAX_BODY + 1)
+                .read_to_string(&mut body);
+            if read.is_err() {
+                return text(400, "body is not UTF-8");
+            }
+            if body.len() as u64 > MAX_BODY {
+                return text(413, "body is too large");
+            }
+            let highscore: Highscore = match serde_json::from_str(&body) {
+                Ok(highscore) => highscore,
+                Err(e) => return text(400, e),
+            };
+            match store.submit(mode, highscore) {
+                Ok(()) => text(201, "saved"),
+                Err(rejection) => rejected(rejection),
+            }
+        }
+        // preflight for the web build's JSON posts
+        (Method::Options, "/api/snake-highscores") => text(204, "")
+            .with_header(header("Access-Control-Allow-Methods", "GET, POST"))
+            .with_header(header(
+                "Access-Control-Allow-Headers",
+                "Content-Type",
+            )),
+        (Method::Get, "/snake-leaderboard") => leaderboard_page(store, mode),
+        (
+            _,
+            "/api/snake-highscores"
+            | "/api/snake-highscores/rank"
+            | "/api/snake-highscores/page"
+            | "/snake-leaderboard",
+        ) => text(405, "method not allowed"),
+        _ => text(404, "not found"),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let dir = (!cli.in_memory).then_some(cli.data_dir);
+    let key = cli.signing_key.map(String::into_bytes);
+    let moderator = match &cli.blocklist {
+        Some(path) => match Moderator::with_file(path) {
+            Ok(moderator) => moderator,
+            Err(e) => {
+                eprintln!("couldn't read the blocklist: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Moderator::builtin(),
+    };
+    let mut store = match Store::new(dir) {
+        Ok(store) => store.with_key(key).with_moderator(moderator),
+        Err(e) => {
+            eprintln!("couldn't open the data directory: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let server = match Server::http(&cli.bind) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("couldn't listen on {}: {}", cli.bind, e);
+            std::process::exit(1);
+        }
+    };
+    println!("leaderboard-server => listening on {}", cli.bind);
+
+    // one request at a time, so submissions never race each other
+    for mut request in server.incoming_requests() {
+        let response = handle(&mut store, &mut request);
+        println!(
+            "{} {} => {}",
+            request.method(),
+            request.url(),
+            response.status_code().0
+        );
+        // the client may have gone away already
+        let _ = request.respond(response);
+    }
+}
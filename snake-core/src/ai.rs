@@ -0,0 +1,459 @@
+//! Computer controlled players that drive a [`Simulation`].
+
+use std::collections::VecDeque;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::game_mode::GameRules;
+use crate::sim::{self, Simulation, BOARD_CELLS};
+use crate::snake::Direction;
+use crate::Position;
+
+pub trait Strategy {
+    fn name(&self) -> &'static str;
+
+    /// Picks the direction to turn to this tick, or `None` to keep going
+    fn next_direction(&mut self, sim: &Simulation) -> Option<Direction>;
+}
+
+/// Every strategy by name, for tools that select one from the command line
+pub fn strategy_by_name(name: &str, seed: u64) -> Option<Box<dyn Strategy>> {
+    match name {
+        "random" => Some(Box::new(Wanderer::new(seed))),
+        "greedy" => Some(Box::new(Greedy)),
+        "pathfinder" => Some(Box::new(Pathfinder)),
+        _ => None,
+    }
+}
+
+pub const STRATEGY_NAMES: [&str; 3] = ["random", "greedy", "pathfinder"];
+
+/// Which cells of the board would kill the head if it moved there
+#[derive(Debug, Clone)]
+pub struct Occupancy {
+    cells: Vec<bool>,
+}
+
+impl Occupancy {
+    pub fn new(sim: &Simulation) -> Self {
+        let snake = sim.snake().iter().copied().collect::<Vec<_>>();
+        Self::from_parts(&snake, sim.walls(), sim.rules())
+    }
+
+    pub fn from_parts(
+        snake: &[Position],
+        walls: &[Position],
+        rules: &GameRules,
+    ) -> Self {
+        let mut occupancy = Self {
+            cells: vec![false; (BOARD_CELLS * BOARD_CELLS) as usize],
+        };
+        for pos in snake {
+            occupancy.set(*pos, true);
+        }
+        if rules.do_collide_walls {
+            for pos in walls {
+                occupancy.set(*pos, true);
+            }
+        }
+        occupancy
+    }
+
+    fn index(pos: Position) -> Option<usize> {
+        sim::in_bounds(pos).then(|| (pos.y * BOARD_CELLS + pos.x) as usize)
+    }
+
+    pub fn is_blocked(&self, pos: Position) -> bool {
+        Self::index(pos).is_none_or(|i| self.cells[i])
+    }
+
+    pub fn set(&mut self, pos: Position, blocked: bool) {
+        if let Some(i) = Self::index(pos) {
+            self.cells[i] = blocked;
+        }
+    }
+
+    /// Number of free cells reachable from `start`
+    pub fn reachable_area(&self, start: Position) -> usize {
+        if self.is_blocked(start) {
+            return 0;
+        }
+        let mut seen = self.clone();
+        let mut queue = VecDeque::from([start]);
+        seen.set(start, true);
+        let mut area = 0;
+        while let Some(pos) = queue.pop_front() {
+            area += 1;
+            for dir in Direction::ALL {
+                let next = sim::advance(pos, dir);
+                if !seen.is_blocked(next) {
+                    seen.set(next, true);
+                    queue.push_back(next);
+                }
+            }
+        }
+        area
+    }
+
+    /// Can `to` be reached from `from` through free cells? `to` itself may
+    /// be blocked.
+    pub fn path_exists(&self, from: Position, to: Position) -> bool {
+        let mut seen = self.clone();
+        seen.set(to, false);
+        let mut queue = VecDeque::from([from]);
+        while let Some(pos) = queue.pop_front() {
+            for dir in Direction::ALL {
+                let next = sim::advance(pos, dir);
+                if next == to {
+                    return true;
+                }
+                if !seen.is_blocked(next) {
+                    seen.set(next, true);
+                    queue.push_back(next);
+                }
+            }
+        }
+        false
+    }
+
+    /// Shortest path of free cells from `start` (exclusive) to `goal`
+    /// (inclusive), never reversing out of `heading` on the first move
+    pub fn shortest_path(
+        &self,
+        start: Position,
+        heading: Direction,
+        goal: Position,
+    ) -> Option<Vec<Position>> {
+        let mut came_from: Vec<Option<Position>> =
+            vec![None; self.cells.len()];
+        let mut seen = self.clone();
+        let mut queue = VecDeque::new();
+        for dir in Direction::ALL {
+            if dir == heading.opposite() {
+                continue;
+            }
+            let next = sim::advance(start, dir);
+            if !seen.is_blocked(next) {
+                seen.set(next, true);
+                came_from[Self::index(next).unwrap()] = Some(start);
+                queue.push_back(next);
+            }
+        }
+
+        while let Some(pos) = queue.pop_front() {
+            if pos == goal {
+                let mut path = vec![pos];
+                let mut current = pos;
+                while let Some(prev) = came_from[Self::index(current).unwrap()]
+                {
+                    if prev == start {
+                        break;
+                    }
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for dir in Direction::ALL {
+                let next = sim::advance(pos, dir);
+                if !seen.is_blocked(next) {
+                    seen.set(next, true);
+                    came_from[Self::index(next).unwrap()] = Some(pos);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Partial paths [`safe_path`] keeps through each cell. A single one would
+/// only ever find the shortest path, a few more let the search go around
+/// the spot where that path would box the snake in.
+const ROUTES_PER_CELL: u8 = 4;
+
+/// The shortest path from the head to `food` after which the snake can
+/// still reach its tail, so following it can't box the snake in.
+/// `snake` lists the head first.
+///
+/// The search keeps [`ROUTES_PER_CELL`] partial paths through each cell,
+/// so in a crowded board a safe path that needs more routes than that may
+/// not be found.
+pub fn safe_path(
+    snake: &[Position],
+    heading: Direction,
+    food: Position,
+    walls: &[Position],
+    rules: &GameRules,
+) -> Option<Vec<Position>> {
+    let occupancy = Occupancy::from_parts(snake, walls, rules);
+    let mut routes = vec![0; occupancy.cells.len()];
+
+    // every partial path as its last cell and the path it extends
+    let mut steps: Vec<(Position, Option<usize>)> = Vec::new();
+    let mut queue = VecDeque::new();
+    let mut extend = |steps: &mut Vec<_>,
+                      queue: &mut VecDeque<usize>,
+                      pos,
+                      from: Option<usize>| {
+        let Some(i) = Occupancy::index(pos) else {
+            return;
+        };
+        if occupancy.is_blocked(pos) || routes[i] == ROUTES_PER_CELL {
+            return;
+        }
+        routes[i] += 1;
+        steps.push((pos, from));
+        queue.push_back(steps.len() - 1);
+    };
+    for dir in Direction::ALL {
+        if dir != heading.opposite() {
+            extend(&mut steps, &mut queue, sim::advance(snake[0], dir), None);
+        }
+    }
+
+    let path_to = |steps: &[(Position, Option<usize>)], mut i: usize| {
+        let mut path = vec![steps[i].0];
+        while let Some(prev) = steps[i].1 {
+            path.push(steps[prev].0);
+            i = prev;
+        }
+        path.reverse();
+        path
+    };
+    while let Some(i) = queue.pop_front() {
+        let path = path_to(&steps, i);
+        let pos = steps[i].0;
+        if pos == food {
+            if leaves_way_to_tail(snake, &path, walls, rules) {
+                return Some(path);
+            }
+            continue;
+        }
+        for dir in Direction::ALL {
+            let next = sim::advance(pos, dir);
+            // a path can't cross itself
+            if !path.contains(&next) {
+                extend(&mut steps, &mut queue, next, Some(i));
+            }
+        }
+    }
+    None
+}
+
+/// Can the snake still reach its tail once it has followed `path` and
+/// eaten at the end of it?
+fn leaves_way_to_tail(
+    snake: &[Position],
+    path: &[Position],
+    walls: &[Position],
+    rules: &GameRules,
+) -> bool {
+    let body = path
+        .iter()
+        .rev()
+        .chain(snake.iter())
+        .copied()
+        .take(snake.len() + 1)
+        .collect::<Vec<_>>();
+    let tail = *body.last().unwrap();
+    let mut after =
+        Occupancy::from_parts(&body[..body.len() - 1], walls, rules);
+    after.set(body[0], false);
+    after.path_exists(body[0], tail)
+}
+
+/// Free cells in pockets too small to hold the snake, which it couldn't
+/// leave again once it entered them
+pub fn trap_cells(
+    snake: &[Position],
+    walls: &[Position],
+    rules: &GameRules,
+) -> Vec<Position> {
+    let mut seen = Occupancy::from_parts(snake, walls, rules);
+    let mut traps = Vec::new();
+    for y in 0..BOARD_CELLS {
+        for x in 0..BOARD_CELLS {
+            let start = Position::new(x, y);
+            if seen.is_blocked(start) {
+                continue;
+            }
+            let mut pocket = vec![start];
+            let mut queue = VecDeque::from([start]);
+            seen.set(start, true);
+            while let Some(pos) = queue.pop_front() {
+                for dir in Direction::ALL {
+                    let next = sim::advance(pos, dir);
+                    if !seen.is_blocked(next) {
+                        seen.set(next, true);
+                        pocket.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            if pocket.len() < snake.len() {
+                traps.extend(pocket);
+            }
+        }
+    }
+    traps
+}
+
+/// The direction that takes the head from `from` to the adjacent `to`
+pub fn direction_between(from: Position, to: Position) -> Option<Direction> {
+    Direction::ALL
+        .into_iter()
+        .find(|dir| sim::advance(from, *dir) == to)
+}
+
+/// Non-fatal directions, ordered by how much room they leave
+fn safe_directions(sim: &Simulation) -> Vec<Direction> {
+    let occupancy = Occupancy::new(sim);
+    let mut dirs = Direction::ALL
+        .into_iter()
+        .filter(|dir| *dir != sim.heading().opposite() && !sim.is_fatal(*dir))
+        .map(|dir| {
+            let area =
+                occupancy.reachable_area(sim::advance(sim.head(), dir));
+            (dir, area)
+        })
+        .collect::<Vec<_>>();
+    dirs.sort_by_key(|(_, area)| std::cmp::Reverse(*area));
+    dirs.into_iter().map(|(dir, _)| dir).collect()
+}
+
+/// Moves in a random direction that doesn't immediately end the game
+pub struct Wanderer {
+    rng: StdRng,
+}
+
+impl Wanderer {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for Wanderer {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn next_direction(&mut self, sim: &Simulation) -> Option<Direction> {
+        let safe = Direction::ALL
+            .into_iter()
+            .filter(|dir| !sim.is_fatal(*dir))
+            .collect::<Vec<_>>();
+        safe.choose(&mut self.rng).copied()
+    }
+}
+
+/// Heads straight for the food, only avoiding immediately fatal moves
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn next_direction(&mut self, sim: &Simulation) -> Option<Direction> {
+        let food = sim.food()?;
+        Direction::ALL
+            .into_iter()
+            .filter(|dir| !sim.is_fatal(*dir))
+            .min_by_key(|dir| {
+                let pos = sim::advance(sim.head(), *dir);
+                (pos.x - food.x).abs() + (pos.y - food.y).abs()
+            })
+    }
+}
+
+/// Follows the shortest path to the food, falling back to whichever move
+/// leaves the most room when the food can't be reached
+pub struct Pathfinder;
+
+impl Strategy for Pathfinder {
+    fn name(&self) -> &'static str {
+        "pathfinder"
+    }
+
+    fn next_direction(&mut self, sim: &Simulation) -> Option<Direction> {
+        let occupancy = Occupancy::new(sim);
+        let path = sim.food().and_then(|food| {
+            occupancy.shortest_path(sim.head(), sim.heading(), food)
+        });
+        if let Some(first) = path.and_then(|path| path.first().copied()) {
+            let dir = direction_between(sim.head(), first)?;
+            let area = occupancy.reachable_area(first);
+            if area > sim.snake().len() {
+                return Some(dir);
+            }
+        }
+        safe_directions(sim).first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(cells: &[(i32, i32)]) -> Vec<Position> {
+        cells.iter().map(|(x, y)| Position::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn safe_path_goes_around_a_trap() {
+        // the food sits in a notch of the body, reached directly the snake
+        // would seal itself off from its tail
+        let snake = cells(&[
+            (3, 5),
+            (3, 4),
+            (3, 3),
+            (4, 3),
+            (5, 3),
+            (5, 4),
+            (5, 5),
+            (6, 5),
+        ]);
+        let food = Position::new(4, 4);
+        let rules = GameRules::CLASSIC;
+
+        let occupancy = Occupancy::from_parts(&snake, &[], &rules);
+        let shortest = occupancy
+            .shortest_path(snake[0], Direction::Up, food)
+            .unwrap();
+        assert_eq!(shortest, cells(&[(4, 5), (4, 4)]));
+        assert!(!leaves_way_to_tail(&snake, &shortest, &[], &rules));
+
+        let path = safe_path(&snake, Direction::Up, food, &[], &rules);
+        assert_eq!(path, Some(cells(&[(3, 6), (4, 6), (4, 5), (4, 4)])));
+    }
+
+    #[test]
+    fn safe_path_takes_the_shortest_path_when_it_is_safe() {
+        let snake = cells(&[(10, 10), (10, 9)]);
+        let food = Position::new(12, 10);
+        let path =
+            safe_path(&snake, Direction::Up, food, &[], &GameRules::CLASSIC);
+        assert_eq!(path, Some(cells(&[(11, 10), (12, 10)])));
+    }
+
+    #[test]
+    fn no_safe_path_into_a_dead_end() {
+        // walls leave the food at the end of a one cell wide corridor
+        let walls = cells(&[(0, 1), (1, 1), (2, 1), (3, 1)]);
+        let snake = cells(&[(6, 3), (6, 2)]);
+        let path = safe_path(
+            &snake,
+            Direction::Up,
+            Position::new(0, 0),
+            &walls,
+            &GameRules::WALLS,
+        );
+        assert_eq!(path, None);
+    }
+}
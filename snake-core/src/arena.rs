@@ -0,0 +1,614 @@
+//! Several snakes sharing one board, and the tournament bookkeeping used by
+//! the `snake-arena` server. Movement and collisions follow the same rules
+//! as [`crate::sim`], generalised to any number of snakes.
+
+use std::collections::{HashSet, VecDeque};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::game_mode::GameRules;
+use crate::sim::{self, DeathCause, BOARD_CELLS, BOARD_MAX};
+use crate::snake::Direction;
+use crate::Position;
+
+#[derive(Debug, Clone)]
+pub struct ArenaSnake {
+    pub id: usize,
+    /// Every segment, head first. Empty once the snake is eliminated.
+    pub body: VecDeque<Position>,
+    pub heading: Direction,
+    pub score: u32,
+    pub death: Option<DeathCause>,
+    /// The tick on which the snake was eliminated
+    pub eliminated_at: Option<u64>,
+}
+
+impl ArenaSnake {
+    pub fn is_alive(&self) -> bool {
+        self.death.is_none()
+    }
+}
+
+/// Starts on one row of the board. Starts are kept three cells apart so no
+/// snake starts on, or next to, another one.
+const PER_ROW: usize = BOARD_CELLS as usize / 3 - 1;
+
+/// Snakes that fit on one board
+pub const MAX_SNAKES: usize = PER_ROW * PER_ROW;
+
+/// One match between several snakes
+#[derive(Debug, Clone)]
+pub struct ArenaBoard {
+    rules: GameRules,
+    rng: StdRng,
+    snakes: Vec<ArenaSnake>,
+    food: Vec<Position>,
+    walls: Vec<Position>,
+    wall_queue: Vec<Position>,
+    ticks: u64,
+}
+
+impl ArenaBoard {
+    /// Places a snake for each id evenly across the middle of the board,
+    /// in more rows once a single row is full. Panics with more than
+    /// [`MAX_SNAKES`] ids.
+    pub fn new(ids: &[usize], rules: GameRules, seed: u64) -> Self {
+        assert!(ids.len() <= MAX_SNAKES, "too many snakes for one board");
+        let mut rng = StdRng::seed_from_u64(seed);
+        let per_row = PER_ROW.min(ids.len()).max(1);
+        let rows = ids.len().div_ceil(per_row);
+        let spacing_x = BOARD_CELLS / (per_row as i32 + 1);
+        let spacing_y = BOARD_CELLS / (rows as i32 + 1);
+        let snakes = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let heading: Direction = rng.gen::<u8>().into();
+                let head = Position::new(
+                    spacing_x * ((i % per_row) as i32 + 1),
+                    spacing_y * ((i / per_row) as i32 + 1),
+                );
+                ArenaSnake {
+                    id: *id,
+                    body: VecDeque::from([
+                        head,
+                        sim::advance(head, heading.opposite()),
+                    ]),
+                    heading,
+                    score: 0,
+                    death: None,
+                    eliminated_at: None,
+                }
+            })
+            .collect();
+
+        let mut board = Self {
+            rules,
+            rng,
+            snakes,
+            food: Vec::new(),
+            walls: Vec::new(),
+            wall_queue: Vec::new(),
+            ticks: 0,
+        };
+        board.refill_food();
+        board
+    }
+
+    pub fn snakes(&self) -> &[ArenaSnake] {
+        &self.snakes
+    }
+
+    pub fn food(&self) -> &[Position] {
+        &self.food
+    }
+
+    pub fn walls(&self) -> &[Position] {
+        &self.walls
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn alive(&self) -> usize {
+        self.snakes.iter().filter(|s| s.is_alive()).count()
+    }
+
+    /// A match is over once at most one snake is left standing
+    pub fn is_over(&self) -> bool {
+        if self.snakes.len() > 1 {
+            self.alive() <= 1
+        } else {
+            self.alive() == 0
+        }
+    }
+
+    /// Moves every living snake at once. `inputs` lines up with
+    /// [`ArenaBoard::snakes`].
+    pub fn step(&mut self, inputs: &[Option<Direction>]) {
+        if self.is_over() {
+            return;
+        }
+        self.ticks += 1;
+
+        let occupied = self
+            .snakes
+            .iter()
+            .flat_map(|s| s.body.iter().copied())
+            .collect::<Vec<_>>();
+        for (snake, input) in self.snakes.iter_mut().zip(inputs) {
+            if snake.is_alive() {
+                snake.heading = sim::turn(snake.heading, *input);
+                let head = sim::advance(snake.body[0], snake.heading);
+                snake.body.push_front(head);
+            }
+        }
+
+        let heads = self
+            .snakes
+            .iter()
+            .filter(|s| s.is_alive())
+            .map(|s| (s.id, s.body[0]))
+            .collect::<Vec<_>>();
+        for snake in self.snakes.iter_mut().filter(|s| s.is_alive()) {
+            let head = snake.body[0];
+            let own_body =
+                snake.body.iter().skip(1).copied().collect::<Vec<_>>();
+            let head_on =
+                heads.iter().any(|(id, h)| *id != snake.id && *h == head);
+            let cause =
+                sim::check_collision(head, &own_body, &self.walls, &self.rules)
+                    .or_else(|| {
+                        (head_on || occupied.contains(&head))
+                            .then_some(DeathCause::OtherSnake)
+                    });
+            snake.death = cause;
+        }
+
+        let ticks = self.ticks;
+        for snake in self.snakes.iter_mut() {
+            if snake.death.is_some() {
+                if snake.eliminated_at.is_none() {
+                    snake.eliminated_at = Some(ticks);
+                }
+                snake.body.clear();
+                continue;
+            }
+
+            let head = snake.body[0];
+            if let Some(i) = self.food.iter().position(|f| *f == head) {
+                self.food.swap_remove(i);
+                snake.score += 1;
+                if self.rules.do_spawn_walls {
+                    self.wall_queue.push(head);
+                }
+            } else {
+                snake.body.pop_back();
+            }
+        }
+
+        let occupied = self
+            .snakes
+            .iter()
+            .flat_map(|s| s.body.iter().copied())
+            .collect::<Vec<_>>();
+        let (blocked, free): (Vec<_>, Vec<_>) = self
+            .wall_queue
+            .drain(..)
+            .partition(|pos| occupied.contains(pos));
+        self.wall_queue = blocked;
+        self.walls.extend(free);
+        self.refill_food();
+    }
+
+    /// Keeps one piece of food on the board per living snake
+    fn refill_food(&mut self) {
+        let occupied = self
+            .snakes
+            .iter()
+            .flat_map(|s| s.body.iter().copied())
+            .chain(self.food.iter().copied())
+            .filter(|p| p.x < BOARD_MAX && p.y < BOARD_MAX)
+            .collect::<HashSet<_>>();
+        let free = (BOARD_MAX * BOARD_MAX) as usize - occupied.len();
+        let wanted = self.alive().saturating_sub(self.food.len()).min(free);
+
+        for _ in 0..wanted {
+            let mut food =
+                crate::food::generate_food_coords_with(&mut self.rng);
+            while occupied.contains(&food) || self.food.contains(&food) {
+                food = crate::food::generate_food_coords_with(&mut self.rng);
+            }
+            self.food.push(food);
+        }
+    }
+
+    /// Snake ids from first to last place. Survivors rank above eliminated
+    /// snakes, later eliminations above earlier ones, then by score.
+    pub fn placements(&self) -> Vec<usize> {
+        let mut order = self.snakes.iter().collect::<Vec<_>>();
+        order.sort_by_key(|s| {
+            let survived = s.eliminated_at.unwrap_or(u64::MAX);
+            (std::cmp::Reverse(survived), std::cmp::Reverse(s.score))
+        });
+        order.into_iter().map(|s| s.id).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Every combination of players meets once
+    RoundRobin,
+    /// Players with similar points meet, for the given number of rounds
+    Swiss { rounds: usize },
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct Standing {
+    pub id: usize,
+    pub name: String,
+    pub points: u32,
+    pub matches: u32,
+    pub wins: u32,
+    pub byes: u32,
+    pub total_score: u32,
+    /// Left the tournament and is no longer scheduled
+    pub withdrawn: bool,
+}
+
+/// Points, wins and match history across a tournament
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    pub standings: Vec<Standing>,
+    match_size: usize,
+    played: HashSet<Vec<usize>>,
+}
+
+impl Tournament {
+    pub fn new(names: Vec<String>, match_size: usize) -> Self {
+        Self {
+            standings: names
+                .into_iter()
+                .enumerate()
+                .map(|(id, name)| Standing {
+                    id,
+                    name,
+                    ..Default::default()
+                })
+                .collect(),
+            match_size: match_size.max(2),
+            played: HashSet::new(),
+        }
+    }
+
+    /// Stops scheduling a player who has left. Matches they already played
+    /// still count.
+    pub fn withdraw(&mut self, id: usize) {
+        self.standings[id].withdrawn = true;
+    }
+
+    /// Whether a player is still scheduled
+    pub fn is_playing(&self, id: usize) -> bool {
+        !self.standings[id].withdrawn
+    }
+
+    /// Players still taking part, by id
+    fn playing(&self) -> Vec<usize> {
+        self.standings
+            .iter()
+            .filter(|s| !s.withdrawn)
+            .map(|s| s.id)
+            .collect()
+    }
+
+    /// Every group of `match_size` players, each played once
+    pub fn round_robin(&self) -> Vec<Vec<usize>> {
+        let players = self.playing();
+        let size = self.match_size.min(players.len());
+        let mut matches = Vec::new();
+        let mut group = Vec::with_capacity(size);
+        combinations(&players, size, 0, &mut group, &mut matches);
+        matches
+    }
+
+    /// Groups players by current points, avoiding rematches where possible.
+    /// A player left over gets a bye worth winning a full match. Only the
+    /// last group of a round can be short, and it takes everyone left, so
+    /// a round with a bye has only full matches and the bye pays the same
+    /// as any win in it.
+    pub fn swiss_round(&mut self) -> Vec<Vec<usize>> {
+        let mut pool = self
+            .standings
+            .iter()
+            .filter(|s| !s.withdrawn)
+            .map(|s| (s.id, s.points))
+            .collect::<Vec<_>>();
+        pool.sort_by_key(|(id, points)| (std::cmp::Reverse(*points), *id));
+        let mut pool = pool.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+        let mut matches = Vec::new();
+        while pool.len() >= 2 {
+            let size = self.match_size.min(pool.len());
+            let mut group = vec![pool.remove(0)];
+            while group.len() < size {
+                // the best placed opponent not yet met, else the best placed
+                let pick = pool
+                    .iter()
+                    .position(|id| {
+                        let mut key = group.clone();
+                        key.push(*id);
+                        key.sort_unstable();
+                        !self
+                            .played
+                            .iter()
+                            .any(|m| key.iter().all(|p| m.contains(p)))
+                    })
+                    .unwrap_or(0);
+                group.push(pool.remove(pick));
+            }
+            matches.push(group);
+        }
+
+        for id in pool {
+            let standing = &mut self.standings[id];
+            standing.byes += 1;
+            standing.points += self.match_size as u32 - 1;
+        }
+        matches
+    }
+
+    /// Records a finished match. Last place scores nothing and every place
+    /// above it one point more.
+    pub fn record(&mut self, board: &ArenaBoard) {
+        let placements = board.placements();
+        let players = placements.len() as u32;
+        for (place, id) in placements.iter().enumerate() {
+            let standing = &mut self.standings[*id];
+            standing.matches += 1;
+            standing.points += players - 1 - place as u32;
+            if place == 0 {
+                standing.wins += 1;
+            }
+        }
+        for snake in board.snakes() {
+            self.standings[snake.id].total_score += snake.score;
+        }
+
+        let mut key = placements;
+        key.sort_unstable();
+        self.played.insert(key);
+    }
+
+    /// Standings from first to last
+    pub fn ranking(&self) -> Vec<Standing> {
+        let mut ranking = self.standings.clone();
+        ranking.sort_by_key(|s| {
+            (
+                std::cmp::Reverse(s.points),
+                std::cmp::Reverse(s.wins),
+                std::cmp::Reverse(s.total_score),
+                s.id,
+            )
+        });
+        ranking
+    }
+}
+
+fn combinations(
+    players: &[usize],
+    size: usize,
+    start: usize,
+    group: &mut Vec<usize>,
+    out: &mut Vec<Vec<usize>>,
+) {
+    if group.len() == size {
+        out.push(group.clone());
+        return;
+    }
+    for (i, id) in players.iter().enumerate().skip(start) {
+        group.push(*id);
+        combinations(players, size, i + 1, group, out);
+        group.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_are_apart_for_every_match_size() {
+        for count in 1..=MAX_SNAKES {
+            let ids = (0..count).collect::<Vec<_>>();
+            let board = ArenaBoard::new(&ids, GameRules::CLASSIC, 0);
+            let heads = board.snakes().iter().map(|s| s.body[0]);
+            let heads = heads.collect::<Vec<_>>();
+            for (i, a) in heads.iter().enumerate() {
+                assert!(sim::in_bounds(*a), "{} snakes", count);
+                for b in &heads[i + 1..] {
+                    let apart = (a.x - b.x).abs().max((a.y - b.y).abs());
+                    assert!(apart >= 3, "{} snakes: {:?} {:?}", count, a, b);
+                }
+            }
+            assert_eq!(board.alive(), count);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "too many snakes")]
+    fn rejects_too_many_snakes() {
+        let ids = (0..=MAX_SNAKES).collect::<Vec<_>>();
+        ArenaBoard::new(&ids, GameRules::CLASSIC, 0);
+    }
+
+    /// A two snake board with the given bodies, heads first, and food
+    fn arena(
+        rules: GameRules,
+        bodies: [(&[(i32, i32)], Direction); 2],
+        food: &[(i32, i32)],
+    ) -> ArenaBoard {
+        let mut board = ArenaBoard::new(&[0, 1], rules, 0);
+        for (snake, (body, heading)) in board.snakes.iter_mut().zip(bodies) {
+            snake.body =
+                body.iter().map(|(x, y)| Position::new(*x, *y)).collect();
+            snake.heading = heading;
+        }
+        board.food = food.iter().map(|(x, y)| Position::new(*x, *y)).collect();
+        board
+    }
+
+    /// A finished match whose placements are `order`
+    fn finished(order: &[usize]) -> ArenaBoard {
+        let mut board = ArenaBoard::new(order, GameRules::CLASSIC, 0);
+        let players = order.len();
+        for (place, snake) in board.snakes.iter_mut().enumerate().skip(1) {
+            snake.death = Some(DeathCause::OtherSnake);
+            snake.eliminated_at = Some((players - place) as u64);
+            snake.body.clear();
+        }
+        board
+    }
+
+    #[test]
+    fn head_on_collision_kills_both() {
+        let mut board = arena(
+            GameRules::CLASSIC,
+            [
+                (&[(5, 10), (4, 10)], Direction::Right),
+                (&[(7, 10), (8, 10)], Direction::Left),
+            ],
+            &[(0, 0)],
+        );
+        board.step(&[None, None]);
+        for snake in board.snakes() {
+            assert_eq!(snake.death, Some(DeathCause::OtherSnake));
+            assert_eq!(snake.eliminated_at, Some(1));
+            assert!(snake.body.is_empty());
+        }
+        assert!(board.is_over());
+    }
+
+    #[test]
+    fn running_into_a_body_is_other_snake() {
+        let mut board = arena(
+            GameRules::CLASSIC,
+            [
+                (&[(5, 10), (5, 9)], Direction::Up),
+                (&[(6, 11), (5, 11), (4, 11)], Direction::Right),
+            ],
+            &[(0, 0)],
+        );
+        board.step(&[None, None]);
+        assert_eq!(board.snakes()[0].death, Some(DeathCause::OtherSnake));
+        assert!(board.snakes()[1].is_alive());
+        assert_eq!(board.snakes()[1].body[0], Position::new(7, 11));
+        assert!(board.is_over());
+        assert_eq!(board.placements(), [1, 0]);
+    }
+
+    #[test]
+    fn eating_grows_and_queues_a_wall() {
+        let bodies: [(&[(i32, i32)], Direction); 2] = [
+            (&[(5, 10), (4, 10)], Direction::Right),
+            (&[(15, 5), (15, 4)], Direction::Up),
+        ];
+        let mut board = arena(GameRules::WALLS, bodies, &[(6, 10)]);
+        board.step(&[None, None]);
+        let snake = &board.snakes()[0];
+        assert_eq!(snake.score, 1);
+        assert_eq!(snake.body.len(), 3);
+        assert_eq!(snake.body[0], Position::new(6, 10));
+        assert_eq!(board.snakes()[1].body.len(), 2);
+        // the wall waits until the snake has moved off the cell
+        assert_eq!(board.wall_queue, [Position::new(6, 10)]);
+        assert!(board.walls().is_empty());
+        assert_eq!(board.food().len(), 2);
+
+        let mut board = arena(GameRules::CLASSIC, bodies, &[(6, 10)]);
+        board.step(&[None, None]);
+        assert_eq!(board.snakes()[0].score, 1);
+        assert!(board.wall_queue.is_empty());
+    }
+
+    #[test]
+    fn placements_rank_survivors_then_later_eliminations_then_score() {
+        let mut board = ArenaBoard::new(&[0, 1, 2, 3], GameRules::CLASSIC, 0);
+        let results = [(None, 1), (Some(3), 9), (Some(5), 2), (Some(5), 3)];
+        for (snake, (eliminated_at, score)) in
+            board.snakes.iter_mut().zip(results)
+        {
+            snake.eliminated_at = eliminated_at;
+            snake.score = score;
+        }
+        assert_eq!(board.placements(), [0, 3, 2, 1]);
+    }
+
+    #[test]
+    fn record_scores_one_point_per_place_above_last() {
+        let names = ["a", "b", "c"].map(String::from).to_vec();
+        let mut tournament = Tournament::new(names, 3);
+        let mut board = finished(&[2, 0, 1]);
+        board.snakes[0].score = 4;
+        board.snakes[1].score = 2;
+        tournament.record(&board);
+
+        let points = tournament.standings.iter().map(|s| s.points);
+        assert_eq!(points.collect::<Vec<_>>(), [1, 0, 2]);
+        let wins = tournament.standings.iter().map(|s| s.wins);
+        assert_eq!(wins.collect::<Vec<_>>(), [0, 0, 1]);
+        assert!(tournament.standings.iter().all(|s| s.matches == 1));
+        assert_eq!(tournament.standings[2].total_score, 4);
+        assert_eq!(tournament.standings[0].total_score, 2);
+
+        let ranking = tournament.ranking().into_iter().map(|s| s.id);
+        assert_eq!(ranking.collect::<Vec<_>>(), [2, 0, 1]);
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let names = ["a", "b", "c", "d"].map(String::from).to_vec();
+        let mut tournament = Tournament::new(names, 2);
+        let matches = tournament.swiss_round();
+        assert_eq!(matches, vec![vec![0, 1], vec![2, 3]]);
+        tournament.record(&finished(&[0, 1]));
+        tournament.record(&finished(&[2, 3]));
+
+        // 0 and 1 are level on top, but have already met
+        tournament.standings[1].points += 1;
+        let matches = tournament.swiss_round();
+        assert_eq!(matches, vec![vec![0, 2], vec![1, 3]]);
+    }
+
+    #[test]
+    fn swiss_gives_the_odd_player_out_a_bye() {
+        let names = ["a", "b", "c"].map(String::from).to_vec();
+        let mut tournament = Tournament::new(names, 2);
+        let matches = tournament.swiss_round();
+        assert_eq!(matches, vec![vec![0, 1]]);
+        assert_eq!(tournament.standings[2].byes, 1);
+        assert_eq!(tournament.standings[2].points, 1);
+        assert_eq!(tournament.standings[2].matches, 0);
+        tournament.record(&finished(&[1, 0]));
+
+        let matches = tournament.swiss_round();
+        assert_eq!(matches, vec![vec![1, 2]]);
+        assert_eq!(tournament.standings[0].byes, 1);
+    }
+
+    #[test]
+    fn withdrawn_players_are_not_scheduled() {
+        let names = ["a", "b", "c", "d"].map(String::from).to_vec();
+        let mut tournament = Tournament::new(names, 2);
+        tournament.withdraw(1);
+        assert!(!tournament.is_playing(1));
+
+        let matches = tournament.round_robin();
+        assert_eq!(matches, vec![vec![0, 2], vec![0, 3], vec![2, 3]]);
+
+        let matches = tournament.swiss_round();
+        assert!(matches.iter().flatten().all(|id| *id != 1));
+        assert_eq!(tournament.standings[1].byes, 0);
+    }
+}
@@ -0,0 +1,249 @@
+//! Plays many headless games with an AI [`Strategy`] and summarises how it
+//! fared, for tuning difficulty and catching changes to the rules.
+
+use std::collections::BTreeMap;
+
+use crate::ai::Strategy;
+use crate::game_mode::GameRules;
+use crate::sim::Simulation;
+
+/// Ticks without eating after which a game is abandoned as stalled
+pub const DEFAULT_STALL_TICKS: u64 = 2000;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GameReport {
+    pub seed: u64,
+    pub score: u32,
+    pub ticks: u64,
+    /// The tick the last food was eaten on, 0 if none was
+    pub last_meal: u64,
+    /// `out_of_bounds`, `self_bite`, `wall`, `stalled` or `perfect`
+    pub ending: &'static str,
+}
+
+/// Plays one game to completion
+pub fn play(
+    strategy: &mut dyn Strategy,
+    rules: GameRules,
+    seed: u64,
+    stall_ticks: u64,
+) -> GameReport {
+    let mut sim = Simulation::new(rules, seed);
+    let mut last_meal = 0;
+    while !sim.is_over() && sim.ticks() - last_meal < stall_ticks {
+        let dir = strategy.next_direction(&sim);
+        if sim.step(dir).ate {
+            last_meal = sim.ticks();
+        }
+    }
+
+    let ending = match sim.death() {
+        Some(cause) => cause.name(),
+        None if sim.is_perfect() => "perfect",
+        None => "stalled",
+    };
+    GameReport {
+        seed,
+        score: sim.score(),
+        ticks: sim.ticks(),
+        last_meal,
+        ending,
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Summary {
+    pub strategy: String,
+    pub mode: String,
+    pub games: usize,
+    pub mean_score: f64,
+    pub median_score: f64,
+    /// Average number of ticks spent per food eaten. Ticks after a game's
+    /// last meal, up to a death or the stall cut-off, aren't counted.
+    pub ticks_per_food: f64,
+    pub perfect_rate: f64,
+    /// How many games ended each way
+    pub endings: BTreeMap<&'static str, usize>,
+}
+
+impl Summary {
+    pub fn new(strategy: &str, rules: &GameRules, reports: &[GameReport]) -> Self {
+        let games = reports.len();
+        let mut scores =
+            reports.iter().map(|r| r.score).collect::<Vec<_>>();
+        scores.sort_unstable();
+
+        let total_score = scores.iter().map(|s| *s as u64).sum::<u64>();
+        let feeding_ticks = reports.iter().map(|r| r.last_meal).sum::<u64>();
+
+        let mut endings = BTreeMap::new();
+        for report in reports {
+            *endings.entry(report.ending).or_insert(0) += 1;
+        }
+        let perfect = endings.get("perfect").copied().unwrap_or(0);
+
+        Self {
+            strategy: strategy.to_string(),
+            mode: rules.name().to_string(),
+            games,
+            mean_score: ratio(total_score, games as u64),
+            median_score: median(&scores),
+            ticks_per_food: ratio(feeding_ticks, total_score),
+            perfect_rate: ratio(perfect as u64, games as u64),
+            endings,
+        }
+    }
+
+    pub fn csv_row(&self) -> String {
+        let mut row = format!(
+            "{},{},{},{:.3},{:.1},{:.3},{:.4}",
+            self.strategy,
+            self.mode,
+            self.games,
+            self.mean_score,
+            self.median_score,
+            self.ticks_per_food,
+            self.perfect_rate,
+        );
+        for ending in ENDINGS {
+            let count = self.endings.get(ending).copied().unwrap_or(0);
+            row.push_str(&format!(",{}", count));
+        }
+        row
+    }
+}
+
+/// The middle of `sorted`, or the mean of the middle two
+fn median(sorted: &[u32]) -> f64 {
+    match sorted.len() {
+        0 => 0.,
+        n if n.is_multiple_of(2) => {
+            (sorted[n / 2 - 1] as f64 + sorted[n / 2] as f64) / 2.
+        }
+        n => sorted[n / 2] as f64,
+    }
+}
+
+fn ratio(num: u64, den: u64) -> f64 {
+    if den == 0 {
+        0.
+    } else {
+        num as f64 / den as f64
+    }
+}
+
+/// Endings that get their own column in CSV output
+pub const ENDINGS: [&str; 5] =
+    ["out_of_bounds", "self_bite", "wall", "stalled", "perfect"];
+
+pub fn csv_header() -> String {
+    let mut header = String::from(
+        "strategy,mode,games,mean_score,median_score,ticks_per_food,\
+         perfect_rate",
+    );
+    for ending in ENDINGS {
+        header.push(',');
+        header.push_str(ending);
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snake::Direction;
+
+    /// Never turns
+    struct Straight;
+
+    impl Strategy for Straight {
+        fn name(&self) -> &'static str {
+            "straight"
+        }
+
+        fn next_direction(&mut self, _: &Simulation) -> Option<Direction> {
+            None
+        }
+    }
+
+    fn report(
+        score: u32,
+        last_meal: u64,
+        ticks: u64,
+        ending: &'static str,
+    ) -> GameReport {
+        GameReport {
+            seed: 0,
+            score,
+            ticks,
+            last_meal,
+            ending,
+        }
+    }
+
+    #[test]
+    fn play_runs_a_game_to_its_end() {
+        // seed 13 eats once going straight, then leaves the board
+        let report = play(&mut Straight, GameRules::CLASSIC, 13, 2000);
+        assert_eq!(report.seed, 13);
+        assert_eq!(report.score, 1);
+        assert_eq!(report.ticks, 11);
+        assert!(report.last_meal > 0 && report.last_meal < 11);
+        assert_eq!(report.ending, "out_of_bounds");
+    }
+
+    #[test]
+    fn play_abandons_stalled_games() {
+        let report = play(&mut Straight, GameRules::CLASSIC, 0, 3);
+        assert_eq!(report.ticks, 3);
+        assert_eq!(report.last_meal, 0);
+        assert_eq!(report.ending, "stalled");
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&[]), 0.);
+        assert_eq!(median(&[4]), 4.);
+        assert_eq!(median(&[1, 2, 9]), 2.);
+        assert_eq!(median(&[1, 2, 3, 9]), 2.5);
+    }
+
+    #[test]
+    fn summary_adds_up_reports() {
+        let reports = [
+            report(4, 36, 40, "wall"),
+            report(0, 0, 10, "self_bite"),
+            report(2, 24, 30, "wall"),
+            report(6, 20, 20, "perfect"),
+            report(0, 0, 2000, "stalled"),
+        ];
+        let summary = Summary::new("greedy", &GameRules::WALLS, &reports);
+        assert_eq!(summary.strategy, "greedy");
+        assert_eq!(summary.mode, "walls");
+        assert_eq!(summary.games, 5);
+        assert_eq!(summary.mean_score, 2.4);
+        assert_eq!(summary.median_score, 2.);
+        // the ticks after each game's last meal don't count
+        assert_eq!(summary.ticks_per_food, 80. / 12.);
+        assert_eq!(summary.perfect_rate, 0.2);
+        assert_eq!(summary.endings["wall"], 2);
+        assert_eq!(summary.endings["self_bite"], 1);
+        assert_eq!(summary.endings["stalled"], 1);
+        assert_eq!(
+            summary.csv_row(),
+            "greedy,walls,5,2.400,2.0,6.667,0.2000,0,1,2,1,1"
+        );
+        let columns = csv_header().split(',').count();
+        assert_eq!(summary.csv_row().split(',').count(), columns);
+    }
+
+    #[test]
+    fn summary_of_nothing_is_zero() {
+        let summary = Summary::new("greedy", &GameRules::CLASSIC, &[]);
+        assert_eq!(summary.games, 0);
+        assert_eq!(summary.mean_score, 0.);
+        assert_eq!(summary.median_score, 0.);
+        assert_eq!(summary.ticks_per_food, 0.);
+        assert_eq!(summary.perfect_rate, 0.);
+    }
+}
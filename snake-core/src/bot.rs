@@ -0,0 +1,231 @@
+//! Lets an external program play the game over a line based JSON protocol.
+//!
+//! Every tick the game writes one line to the bot's stdin:
+//!
+//! `{"type":"tick","tick":3,"width":21,"height":21,"heading":"up",
+//!   "snake":[[10,12],[10,11]],"food":[4,7],"walls":[],"score":0,
+//!   "mode":"classic"}`
+//!
+//! and the bot answers with one line on stdout, `{"direction":"left"}`.
+//! `tick` counts the moves made so far, starting at 0, and `snake` lists
+//! the head first. Answers that don't arrive within the tick
+//! budget, or that can't be parsed, keep the current direction. When the
+//! game ends the bot receives `{"type":"end","score":12}`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+use crate::ai::Strategy;
+use crate::game_mode::GameRules;
+use crate::sim::{Simulation, BOARD_CELLS};
+use crate::snake::Direction;
+use crate::Position;
+
+/// Default time a bot has to answer each tick
+pub const DEFAULT_BUDGET: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Tick {
+        tick: u64,
+        width: i32,
+        height: i32,
+        heading: &'static str,
+        snake: Vec<[i32; 2]>,
+        food: Option<[i32; 2]>,
+        walls: Vec<[i32; 2]>,
+        score: u32,
+        mode: &'static str,
+    },
+    End {
+        score: u32,
+    },
+}
+
+impl BotMessage {
+    pub fn tick(
+        tick: u64,
+        heading: Direction,
+        snake: &[Position],
+        food: Option<Position>,
+        walls: &[Position],
+        score: u32,
+        rules: &GameRules,
+    ) -> Self {
+        let cell = |pos: &Position| [pos.x, pos.y];
+        Self::Tick {
+            tick,
+            width: BOARD_CELLS,
+            height: BOARD_CELLS,
+            heading: direction_name(heading),
+            snake: snake.iter().map(cell).collect(),
+            food: food.as_ref().map(cell),
+            walls: walls.iter().map(cell).collect(),
+            score,
+            mode: rules.name(),
+        }
+    }
+
+    pub fn from_sim(sim: &Simulation) -> Self {
+        let snake = sim.snake().iter().copied().collect::<Vec<_>>();
+        Self::tick(
+            sim.ticks(),
+            sim.heading(),
+            &snake,
+            sim.food(),
+            sim.walls(),
+            sim.score(),
+            sim.rules(),
+        )
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BotReply {
+    direction: String,
+}
+
+fn direction_name(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+fn parse_reply(line: &str) -> Option<Direction> {
+    let reply = serde_json::from_str::<BotReply>(line).ok()?;
+    match reply.direction.as_str() {
+        "up" => Some(Direction::Up),
+        "down" => Some(Direction::Down),
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+/// A running bot executable
+pub struct BotProcess {
+    child: Child,
+    stdin: ChildStdin,
+    replies: Receiver<String>,
+    budget: Duration,
+}
+
+impl BotProcess {
+    /// Starts `command`, split on whitespace into a program and arguments
+    pub fn spawn(command: &str, budget: Duration) -> std::io::Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "bot command is empty",
+            )
+        })?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (tx, replies) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            replies,
+            budget,
+        })
+    }
+
+    /// Sends a message, discarding any answers to earlier ticks
+    pub fn send(&mut self, message: &BotMessage) {
+        while self.replies.try_recv().is_ok() {}
+        let line = serde_json::to_string(message).expect("message serializes");
+        if let Err(e) = writeln!(self.stdin, "{}", line) {
+            eprintln!("Failed to write to bot: {}", e);
+        }
+    }
+
+    /// Time the bot has to answer each tick
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Waits up to the tick budget for the bot's answer
+    pub fn receive(&mut self) -> Option<Direction> {
+        let line = self.replies.recv_timeout(self.budget).ok()?;
+        parse_reply(&line)
+    }
+
+    /// Returns the bot's answer if one has arrived
+    pub fn try_receive(&mut self) -> Result<Option<Direction>, TryRecvError> {
+        self.replies.try_recv().map(|line| parse_reply(&line))
+    }
+}
+
+impl Drop for BotProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Strategy for BotProcess {
+    fn name(&self) -> &'static str {
+        "bot"
+    }
+
+    fn next_direction(&mut self, sim: &Simulation) -> Option<Direction> {
+        self.send(&BotMessage::from_sim(sim));
+        self.receive()
+    }
+}
+
+/// Plays one game without a window, returning how it went
+pub fn run_headless(
+    command: &str,
+    rules: GameRules,
+    seed: u64,
+    budget: Duration,
+) -> std::io::Result<crate::bench::GameReport> {
+    let mut bot = BotProcess::spawn(command, budget)?;
+    let report = crate::bench::play(
+        &mut bot,
+        rules,
+        seed,
+        crate::bench::DEFAULT_STALL_TICKS,
+    );
+    bot.send(&BotMessage::End {
+        score: report.score,
+    });
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_count_from_zero() {
+        let sim = Simulation::new(GameRules::CLASSIC, 0);
+        let BotMessage::Tick { tick, .. } = BotMessage::from_sim(&sim) else {
+            unreachable!()
+        };
+        assert_eq!(tick, 0);
+    }
+}
@@ -0,0 +1,42 @@
+//! How the player steers.
+
+/// How the player steers. The schemes play very differently, so the one
+/// used is stored with each score.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlScheme {
+    /// One action per direction
+    #[default]
+    Absolute,
+    /// Turn left or right of the way the snake is heading
+    Relative,
+    /// A single switch that turns clockwise
+    OneButton,
+}
+
+impl ControlScheme {
+    pub const ALL: [ControlScheme; 3] = [
+        ControlScheme::Absolute,
+        ControlScheme::Relative,
+        ControlScheme::OneButton,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ControlScheme::Absolute => "Absolute",
+            ControlScheme::Relative => "Relative",
+            ControlScheme::OneButton => "One button",
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! A reinforcement learning environment built on [`Simulation`], so agents
+//! are trained against exactly the rules the game ships with.
+
+use crate::game_mode::GameRules;
+use crate::sim::{Simulation, BOARD_CELLS};
+use crate::snake::Direction;
+
+/// The move for one tick, `None` keeps the current heading
+pub type Action = Option<Direction>;
+
+/// Reward given for each kind of transition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewards {
+    pub food: f32,
+    pub death: f32,
+    /// Given on every tick, usually a small penalty to discourage stalling
+    pub step: f32,
+    pub perfect: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            food: 1.,
+            death: -1.,
+            step: 0.,
+            perfect: 10.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Cell {
+    Empty = 0,
+    Head = 1,
+    Body = 2,
+    Food = 3,
+    Wall = 4,
+}
+
+/// The board as a row-major grid, `cells[y * width + x]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Cell>,
+    pub heading: Direction,
+    pub score: u32,
+}
+
+impl Observation {
+    pub fn new(sim: &Simulation) -> Self {
+        let size = BOARD_CELLS as usize;
+        let mut cells = vec![Cell::Empty; size * size];
+        let mut set = |x: i32, y: i32, cell: Cell| {
+            cells[y as usize * size + x as usize] = cell;
+        };
+        for wall in sim.walls() {
+            set(wall.x, wall.y, Cell::Wall);
+        }
+        if let Some(food) = sim.food() {
+            set(food.x, food.y, Cell::Food);
+        }
+        for (i, pos) in sim.snake().iter().enumerate() {
+            // the head is off the board after an out of bounds death
+            if crate::sim::in_bounds(*pos) {
+                let cell = if i == 0 { Cell::Head } else { Cell::Body };
+                set(pos.x, pos.y, cell);
+            }
+        }
+        Self {
+            width: size,
+            height: size,
+            cells,
+            heading: sim.heading(),
+            score: sim.score(),
+        }
+    }
+
+    /// One-hot planes for head, body, food and walls, in that order
+    pub fn to_planes(&self) -> Vec<f32> {
+        let plane = self.width * self.height;
+        let mut planes = vec![0.; plane * 4];
+        for (i, cell) in self.cells.iter().enumerate() {
+            if *cell != Cell::Empty {
+                planes[(*cell as usize - 1) * plane + i] = 1.;
+            }
+        }
+        planes
+    }
+}
+
+/// A single environment: `reset(seed)` then `step(action)` until done
+#[derive(Debug, Clone)]
+pub struct SnakeEnv {
+    rules: GameRules,
+    rewards: Rewards,
+    sim: Simulation,
+    stall_ticks: u64,
+    last_meal: u64,
+}
+
+impl SnakeEnv {
+    pub fn new(rules: GameRules, rewards: Rewards) -> Self {
+        Self {
+            rules,
+            rewards,
+            sim: Simulation::new(rules, 0),
+            stall_ticks: crate::bench::DEFAULT_STALL_TICKS,
+            last_meal: 0,
+        }
+    }
+
+    /// Ends episodes that go this many ticks without eating
+    pub fn with_stall_ticks(mut self, stall_ticks: u64) -> Self {
+        self.stall_ticks = stall_ticks;
+        self
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.sim
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.sim = Simulation::new(self.rules, seed);
+        self.last_meal = 0;
+        Observation::new(&self.sim)
+    }
+
+    /// Whether the episode has ended, by dying or by stalling
+    pub fn is_done(&self) -> bool {
+        self.sim.is_over()
+            || self.sim.ticks() - self.last_meal >= self.stall_ticks
+    }
+
+    /// Moves one tick. Once the episode is done, the last observation is
+    /// returned with no reward until the next `reset`.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        if self.is_done() {
+            return (Observation::new(&self.sim), 0., true);
+        }
+
+        let outcome = self.sim.step(action);
+        let mut reward = self.rewards.step;
+        if outcome.ate {
+            reward += self.rewards.food;
+            self.last_meal = self.sim.ticks();
+        }
+        if outcome.death.is_some() {
+            reward += self.rewards.death;
+        } else if self.sim.is_perfect() {
+            reward += self.rewards.perfect;
+        }
+
+        (Observation::new(&self.sim), reward, self.is_done())
+    }
+}
+
+/// Several environments stepped together. Finished environments are reset
+/// automatically with the next unused seed.
+#[derive(Debug, Clone)]
+pub struct BatchEnv {
+    envs: Vec<SnakeEnv>,
+    next_seed: u64,
+}
+
+impl BatchEnv {
+    pub fn new(count: usize, rules: GameRules, rewards: Rewards) -> Self {
+        Self {
+            envs: vec![SnakeEnv::new(rules, rewards); count],
+            next_seed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    /// Resets environment `i` with seed `seed + i`, wrapping around
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.next_seed = seed.wrapping_add(self.envs.len() as u64);
+        self.envs
+            .iter_mut()
+            .enumerate()
+            .map(|(i, env)| env.reset(seed.wrapping_add(i as u64)))
+            .collect()
+    }
+
+    /// Steps every environment with its matching action. When an episode
+    /// ends, `done` is set and the observation is the first one of the next.
+    pub fn step(
+        &mut self,
+        actions: &[Action],
+    ) -> Vec<(Observation, f32, bool)> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per env");
+
+        // a tick is a few microseconds, far less than handing the batch
+        // to other threads would cost
+        self.envs
+            .iter_mut()
+            .zip(actions)
+            .map(|(env, action)| {
+                let mut result = env.step(*action);
+                if result.2 {
+                    result.0 = env.reset(self.next_seed);
+                    self.next_seed = self.next_seed.wrapping_add(1);
+                }
+                result
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seed 13 eats once going straight ahead, then leaves the board
+    const EATS_ONCE: u64 = 13;
+
+    fn env() -> SnakeEnv {
+        SnakeEnv::new(GameRules::CLASSIC, Rewards::default())
+    }
+
+    fn count(observation: &Observation, cell: Cell) -> usize {
+        observation.cells.iter().filter(|c| **c == cell).count()
+    }
+
+    /// Steps straight ahead until the episode ends, returning the rewards
+    fn play_out(env: &mut SnakeEnv) -> Vec<f32> {
+        let mut rewards = Vec::new();
+        loop {
+            let (_, reward, done) = env.step(None);
+            rewards.push(reward);
+            if done {
+                return rewards;
+            }
+        }
+    }
+
+    #[test]
+    fn reset_is_reproducible() {
+        let mut a = env();
+        let mut b = env();
+        assert_eq!(a.reset(7), b.reset(7));
+        assert_eq!(a.step(None), b.step(None));
+        assert_ne!(a.reset(7), b.reset(8));
+    }
+
+    #[test]
+    fn observation_shows_the_board() {
+        let observation = env().reset(EATS_ONCE);
+        let size = BOARD_CELLS as usize;
+        assert_eq!(observation.cells.len(), size * size);
+        assert_eq!(count(&observation, Cell::Head), 1);
+        assert_eq!(count(&observation, Cell::Body), 1);
+        assert_eq!(count(&observation, Cell::Food), 1);
+        assert_eq!(count(&observation, Cell::Wall), 0);
+        assert_eq!(observation.score, 0);
+
+        let head = crate::sim::START;
+        let at = head.y as usize * observation.width + head.x as usize;
+        assert_eq!(observation.cells[at], Cell::Head);
+
+        let planes = observation.to_planes();
+        let plane = observation.width * observation.height;
+        assert_eq!(planes.len(), plane * 4);
+        assert_eq!(planes[at], 1.);
+        assert_eq!(planes.iter().sum::<f32>(), 3.);
+    }
+
+    #[test]
+    fn rewards_follow_the_run() {
+        let mut env = env();
+        env.reset(EATS_ONCE);
+        let rewards = play_out(&mut env);
+        assert_eq!(rewards.iter().filter(|r| **r == 1.).count(), 1);
+        assert_eq!(*rewards.last().unwrap(), -1.);
+        assert_eq!(env.simulation().score(), 1);
+
+        // a finished episode stays finished
+        let (_, reward, done) = env.step(None);
+        assert_eq!((reward, done), (0., true));
+    }
+
+    #[test]
+    fn step_penalty_is_paid_every_tick() {
+        let rewards = Rewards {
+            step: -0.5,
+            ..Default::default()
+        };
+        let mut env = SnakeEnv::new(GameRules::CLASSIC, rewards);
+        env.reset(EATS_ONCE);
+        let (_, reward, _) = env.step(None);
+        assert_eq!(reward, -0.5);
+    }
+
+    #[test]
+    fn stalled_episodes_end() {
+        let mut env = env().with_stall_ticks(3);
+        // seed 0 never reaches its food going straight ahead
+        env.reset(0);
+        assert_eq!(play_out(&mut env).len(), 3);
+        assert!(!env.simulation().is_over());
+
+        // and stay finished without moving on
+        let (observation, reward, done) = env.step(Some(Direction::Left));
+        assert_eq!((reward, done), (0., true));
+        assert_eq!(env.simulation().ticks(), 3);
+        assert_eq!(observation, Observation::new(env.simulation()));
+        assert!(env.is_done());
+
+        env.reset(0);
+        assert!(!env.is_done());
+    }
+
+    #[test]
+    fn batch_matches_single_envs() {
+        let mut batch =
+            BatchEnv::new(3, GameRules::CLASSIC, Rewards::default());
+        let observations = batch.reset(5);
+        for (i, observation) in observations.iter().enumerate() {
+            assert_eq!(*observation, env().reset(5 + i as u64));
+        }
+
+        let mut singles: Vec<_> = (0..3)
+            .map(|i| {
+                let mut env = env();
+                env.reset(5 + i);
+                env
+            })
+            .collect();
+        let actions = [None, Some(Direction::Left), Some(Direction::Right)];
+        let results = batch.step(&actions);
+        for ((env, action), result) in
+            singles.iter_mut().zip(actions).zip(results)
+        {
+            assert_eq!(env.step(action), result);
+        }
+    }
+
+    #[test]
+    fn batch_resets_finished_envs_with_fresh_seeds() {
+        let mut batch =
+            BatchEnv::new(2, GameRules::CLASSIC, Rewards::default());
+        batch.reset(0);
+        // the first env to finish is reset with seed 2, the next with 3
+        let mut fresh = Vec::new();
+        while fresh.len() < 2 {
+            for result in batch.step(&[None, None]) {
+                if result.2 {
+                    fresh.push(result.0);
+                }
+            }
+        }
+        assert_eq!(fresh[0], env().reset(2));
+        assert_eq!(fresh[1], env().reset(3));
+    }
+}
@@ -0,0 +1,25 @@
+//! Where food lands.
+
+use rand::Rng;
+
+/// A random cell for food, drawn from the given rng so that headless
+/// simulations can be reproduced from a seed
+pub fn generate_food_coords_with<R: Rng + ?Sized>(
+    rng: &mut R,
+) -> crate::Position {
+    let x = (rng.gen::<f32>() * crate::WALL) as i32;
+    let y = (rng.gen::<f32>() * crate::WALL) as i32;
+
+    // reduce probability that food spawns on the wall
+    // 10% chance to regenerate if food is on the wall
+    if (x == 0
+        || x == crate::WALL as i32 - 1
+        || y == 0
+        || y == crate::WALL as i32 - 1)
+        && rng.gen::<f32>() > 0.9
+    {
+        return generate_food_coords_with(rng);
+    }
+
+    crate::Position { x, y }
+}
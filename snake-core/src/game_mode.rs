@@ -0,0 +1,57 @@
+//! The rules each mode is played under.
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct GameRules {
+    pub do_collide_walls: bool,
+    pub do_spawn_walls: bool,
+}
+
+impl GameRules {
+    pub const CLASSIC: Self = Self {
+        do_collide_walls: false,
+        do_spawn_walls: false,
+    };
+
+    pub const WALLS: Self = Self {
+        do_collide_walls: true,
+        do_spawn_walls: true,
+    };
+
+    /// Every mode with a leaderboard of its own
+    pub const ALL: [Self; 2] = [Self::CLASSIC, Self::WALLS];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::CLASSIC),
+            "walls" => Some(Self::WALLS),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match (self.do_collide_walls, self.do_spawn_walls) {
+            (false, false) => "classic",
+            (true, true) => "walls",
+            _ => "custom",
+        }
+    }
+
+    /// The name shown to players
+    pub fn label(&self) -> &'static str {
+        match self.name() {
+            "classic" => "Classic",
+            "walls" => "Walls",
+            _ => "Custom",
+        }
+    }
+}
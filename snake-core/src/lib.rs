@@ -0,0 +1,38 @@
+//! The rules of the game and the records of its runs, without Bevy, shared
+//! by the game, the leaderboard server and the headless tools. The `bevy`
+//! feature lets the game use the types that need it as resources and
+//! components.
+
+pub mod ai;
+pub mod arena;
+pub mod bench;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bot;
+pub mod controls;
+pub mod env;
+pub mod food;
+pub mod game_mode;
+pub mod moderation;
+pub mod replay;
+pub mod score;
+pub mod signing;
+pub mod sim;
+pub mod snake;
+
+pub const TICK_RATE: f32 = 5.; // Number of updates per second
+pub const TICK_INCREASE: f32 = 0.20; // How much to increase tick rate by on eat
+pub const BIG_TICK_INCREASE: f32 = 0.50; // How much to increase tick rate by on eat every 10th score
+pub const WALL: f32 = 20.;
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
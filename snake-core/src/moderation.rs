@@ -0,0 +1,252 @@
+//! Player names. The game checks a name before it sends a score and the
+//! leaderboard server checks it again on the way in, both with a
+//! [`Moderator`], so a name refused by one is refused by the other.
+//!
+//! Names are matched against a blocklist after [`normalize`] folds case,
+//! leetspeak and repeated letters, so `5h1111t` is caught by `shit`.
+//! Names have no spaces to find word boundaries by, so a blocked word is
+//! caught anywhere in a name, unless it lies inside an allowed word: lines
+//! starting with `!` list words such as `scunthorpe` that may contain one.
+//! The built-in list can be extended with a file in the same format, named
+//! by the `RUST_SNAKE_BLOCKLIST` environment variable on native or by the
+//! server's `--blocklist`. Lines starting with `#` are comments.
+
+use std::fmt;
+use std::path::Path;
+
+/// Longest name the game lets players type
+pub const MAX_NAME_LEN: usize = 11;
+
+/// Environment variable naming an extra blocklist file
+pub const BLOCKLIST_ENV: &str = "RUST_SNAKE_BLOCKLIST";
+
+/// The list every build starts with
+const BUILTIN_BLOCKLIST: &str = include_str!("blocklist.txt");
+
+/// Why a name can't be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameProblem {
+    Empty,
+    TooLong,
+    BadCharacter(char),
+    /// The name contains a blocked word
+    Blocked,
+}
+
+impl fmt::Display for NameProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameProblem::Empty => write!(f, "name is empty"),
+            NameProblem::TooLong => {
+                write!(f, "name is longer than {} characters", MAX_NAME_LEN)
+            }
+            NameProblem::BadCharacter(c) => {
+                write!(f, "name may not contain {:?}", c)
+            }
+            NameProblem::Blocked => write!(f, "that name isn't allowed"),
+        }
+    }
+}
+
+impl std::error::Error for NameProblem {}
+
+/// Folds a name into the form blocked words are matched in: lower case,
+/// look-alike digits and symbols turned into letters, and runs of the same
+/// letter squashed into one.
+pub fn normalize(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        let c = match c {
+            '0' => 'o',
+            '1' | '!' | '|' | 'l' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '6' | '9' => 'g',
+            '7' | '+' => 't',
+            '8' => 'b',
+            c if c.is_alphanumeric() => c,
+            // spacing and punctuation don't hide a word
+            _ => continue,
+        };
+        if !normalized.ends_with(c) {
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+/// Checks names against the naming rules and a blocklist
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone)]
+pub struct Moderator {
+    /// Normalized and sorted
+    blocked: Vec<String>,
+    /// Words that may contain a blocked word, normalized and sorted
+    allowed: Vec<String>,
+}
+
+impl Default for Moderator {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Moderator {
+    /// A moderator blocking the words in `list`, one per line, and
+    /// allowing those starting with `!`
+    pub fn from_list(list: &str) -> Self {
+        let mut moderator = Self {
+            blocked: Vec::new(),
+            allowed: Vec::new(),
+        };
+        moderator.extend(list);
+        moderator
+    }
+
+    pub fn builtin() -> Self {
+        Self::from_list(BUILTIN_BLOCKLIST)
+    }
+
+    /// The built-in list with the words in the file at `path` added
+    pub fn with_file(path: &Path) -> std::io::Result<Self> {
+        let mut moderator = Self::builtin();
+        moderator.extend(&std::fs::read_to_string(path)?);
+        Ok(moderator)
+    }
+
+    fn extend(&mut self, list: &str) {
+        let lines = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for line in lines {
+            let (words, word) = match line.strip_prefix('!') {
+                Some(word) => (&mut self.allowed, normalize(word)),
+                None => (&mut self.blocked, normalize(line)),
+            };
+            if !word.is_empty() {
+                words.push(word);
+            }
+        }
+        for words in [&mut self.blocked, &mut self.allowed] {
+            words.sort();
+            words.dedup();
+        }
+    }
+
+    /// Words blocked, as normalized
+    pub fn blocked(&self) -> &[String] {
+        &self.blocked
+    }
+
+    /// Words that may contain a blocked word, as normalized
+    pub fn allowed(&self) -> &[String] {
+        &self.allowed
+    }
+
+    /// Whether a blocked word appears in the normalized `name` outside
+    /// every allowed word
+    fn is_blocked(&self, name: &str) -> bool {
+        // spans of the name covered by allowed words
+        let allowed = self
+            .allowed
+            .iter()
+            .flat_map(|word| {
+                name.match_indices(word.as_str())
+                    .map(|(start, word)| start..start + word.len())
+            })
+            .collect::<Vec<_>>();
+        self.blocked.iter().any(|word| {
+            // every match, overlapping ones too
+            name.char_indices()
+                .map(|(start, _)| start)
+                .filter(|&start| name[start..].starts_with(word.as_str()))
+                .any(|start| {
+                    let end = start + word.len();
+                    !allowed
+                        .iter()
+                        .any(|span| span.start <= start && end <= span.end)
+                })
+        })
+    }
+
+    /// Checks `name` against the naming rules and the blocklist
+    pub fn check(&self, name: &str) -> Result<(), NameProblem> {
+        if name.is_empty() {
+            return Err(NameProblem::Empty);
+        }
+        if name.chars().count() > MAX_NAME_LEN {
+            return Err(NameProblem::TooLong);
+        }
+        if let Some(c) = name.chars().find(|c| !c.is_ascii_alphanumeric()) {
+            return Err(NameProblem::BadCharacter(c));
+        }
+        if self.is_blocked(&normalize(name)) {
+            return Err(NameProblem::Blocked);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_folds_case_leetspeak_and_repeats() {
+        assert_eq!(normalize("SnAkE"), "snake");
+        assert_eq!(normalize("5h1111t"), "shit");
+        assert_eq!(normalize("B!g S4M"), "bigsam");
+        assert_eq!(normalize("Hello"), "heio");
+        assert_eq!(normalize("a-b_c d"), "abcd");
+        assert_eq!(normalize(""), "");
+    }
+
+    #[test]
+    fn check_applies_the_naming_rules() {
+        let moderator = Moderator::from_list("");
+        assert_eq!(moderator.check("Ada"), Ok(()));
+        assert_eq!(moderator.check("Player123"), Ok(()));
+        assert_eq!(moderator.check(""), Err(NameProblem::Empty));
+        assert_eq!(moderator.check("Abcdefghijkl"), Err(NameProblem::TooLong));
+        assert_eq!(
+            moderator.check("Ada Byron"),
+            Err(NameProblem::BadCharacter(' '))
+        );
+        assert_eq!(moderator.check("Zoë"), Err(NameProblem::BadCharacter('ë')));
+    }
+
+    #[test]
+    fn check_finds_blocked_words_anywhere() {
+        let moderator = Moderator::builtin();
+        for name in ["shit", "SHIT", "5h1111t", "BigShitter", "xNaziX"] {
+            assert_eq!(
+                moderator.check(name),
+                Err(NameProblem::Blocked),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn check_lets_allowed_words_through() {
+        let moderator = Moderator::builtin();
+        for name in ["Nazir", "Nazia", "Scunthorpe", "N4Z1R", "Shiitake"] {
+            assert_eq!(moderator.check(name), Ok(()), "{}", name);
+        }
+        // a blocked word outside the allowed one still counts
+        assert_eq!(moderator.check("NazirNazi"), Err(NameProblem::Blocked));
+        assert_eq!(moderator.check("ShitNazir"), Err(NameProblem::Blocked));
+    }
+
+    #[test]
+    fn lists_block_and_allow_normalized_words() {
+        let moderator = Moderator::from_list("# comment\n\nB4D\n!b4dger\n");
+        assert_eq!(moderator.blocked(), ["bad"]);
+        assert_eq!(moderator.allowed(), ["badger"]);
+        assert_eq!(moderator.check("Badger"), Ok(()));
+        assert_eq!(moderator.check("BadBadger"), Err(NameProblem::Blocked));
+    }
+}
@@ -0,0 +1,245 @@
+//! Recorded runs. A [`Replay`] holds everything needed to play a run again
+//! on a [`Simulation`]: the seed, the rules and every turn the snake made.
+//! Leaderboards play submissions back and only accept a score the run
+//! really reaches.
+
+use std::fmt;
+
+use crate::game_mode::GameRules;
+use crate::score::RunStats;
+use crate::sim::Simulation;
+use crate::snake::Direction;
+
+/// Longest run a replay may describe, to bound the work of checking one
+pub const MAX_REPLAY_TICKS: u64 = 200_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub rules: GameRules,
+    /// Ticks the run lasted, including the one it ended on
+    pub ticks: u64,
+    /// The tick each turn was made on, counting from 1, and its direction
+    pub turns: Vec<(u64, Direction)>,
+}
+
+/// Why a replay doesn't back up its score
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The run is longer than [`MAX_REPLAY_TICKS`]
+    TooLong(u64),
+    /// A turn is out of order or outside the run
+    BadTurn(u64),
+    /// The snake died before the recorded end of the run
+    EndedEarly(u64),
+    /// The snake was still alive at the recorded end of the run
+    DidNotEnd,
+    ScoreMismatch {
+        claimed: u32,
+        replayed: u32,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::TooLong(ticks) => {
+                write!(f, "the run is too long ({} ticks)", ticks)
+            }
+            ReplayError::BadTurn(tick) => {
+                write!(f, "the turn on tick {} is out of place", tick)
+            }
+            ReplayError::EndedEarly(tick) => {
+                write!(f, "the snake died early, on tick {}", tick)
+            }
+            ReplayError::DidNotEnd => {
+                write!(f, "the snake was still alive at the end")
+            }
+            ReplayError::ScoreMismatch { claimed, replayed } => write!(
+                f,
+                "the run scores {}, not the {} claimed",
+                replayed, claimed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    pub fn new(seed: u64, rules: GameRules) -> Self {
+        Self {
+            seed,
+            rules,
+            ticks: 0,
+            turns: Vec::new(),
+        }
+    }
+
+    /// Notes one tick of the run and the turn made on it, if any
+    pub fn record(&mut self, input: Option<Direction>) {
+        self.ticks += 1;
+        if let Some(dir) = input {
+            self.turns.push((self.ticks, dir));
+        }
+    }
+
+    /// Plays the run back, returning the game as it stood after its last
+    /// tick
+    pub fn simulate(&self) -> Result<Simulation, ReplayError> {
+        if self.ticks > MAX_REPLAY_TICKS {
+            return Err(ReplayError::TooLong(self.ticks));
+        }
+        let mut sim = Simulation::new(self.rules, self.seed);
+        let mut turns = self.turns.iter().peekable();
+        for tick in 1..=self.ticks {
+            if sim.is_over() {
+                return Err(ReplayError::EndedEarly(sim.ticks()));
+            }
+            let input = turns.next_if(|(at, _)| *at == tick).map(|t| t.1);
+            if let Some((at, _)) = turns.peek() {
+                if *at <= tick {
+                    return Err(ReplayError::BadTurn(*at));
+                }
+            }
+            sim.step(input);
+        }
+        if let Some((at, _)) = turns.next() {
+            return Err(ReplayError::BadTurn(*at));
+        }
+        Ok(sim)
+    }
+
+    /// Checks that the run ends on its last tick with exactly `score`
+    pub fn verify(&self, score: u32) -> Result<(), ReplayError> {
+        let sim = self.simulate()?;
+        if !sim.is_over() {
+            return Err(ReplayError::DidNotEnd);
+        }
+        if sim.score() != score {
+            return Err(ReplayError::ScoreMismatch {
+                claimed: score,
+                replayed: sim.score(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Length, duration, top speed and death cause of the run, as it plays
+    /// back
+    pub fn stats(&self) -> Result<RunStats, ReplayError> {
+        let sim = self.simulate()?;
+        Ok(RunStats {
+            length: sim.snake().len() as u32,
+            ticks: sim.ticks(),
+            seconds: sim.seconds(),
+            // the snake only ever speeds up
+            max_speed: sim.tick_rate(),
+            death: sim.death(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seed 13 eats once going straight ahead, then leaves the board
+    const SEED: u64 = 13;
+
+    /// Plays a run to its end, turning on the given ticks
+    fn play(turns: &[(u64, Direction)]) -> (Replay, u32) {
+        let mut sim = Simulation::new(GameRules::CLASSIC, SEED);
+        let mut replay = Replay::new(SEED, GameRules::CLASSIC);
+        while !sim.is_over() {
+            let tick = sim.ticks() + 1;
+            let input = turns.iter().find(|(at, _)| *at == tick);
+            let input = input.map(|(_, dir)| *dir);
+            sim.step(input);
+            replay.record(input);
+        }
+        (replay, sim.score())
+    }
+
+    #[test]
+    fn recorded_run_verifies() {
+        let (replay, score) = play(&[]);
+        assert_eq!(score, 1);
+        assert_eq!(replay.verify(score), Ok(()));
+
+        let heading = Simulation::new(GameRules::CLASSIC, SEED).heading();
+        let (replay, score) = play(&[(3, heading.clockwise())]);
+        assert_eq!(replay.turns.len(), 1);
+        assert_eq!(replay.verify(score), Ok(()));
+        assert_eq!(replay.stats().unwrap().ticks, replay.ticks);
+    }
+
+    #[test]
+    fn replay_survives_json() {
+        let heading = Simulation::new(GameRules::CLASSIC, SEED).heading();
+        let (replay, score) = play(&[(2, heading.counter_clockwise())]);
+        let json = serde_json::to_string(&replay).unwrap();
+        let read: Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, replay);
+        assert_eq!(read.verify(score), Ok(()));
+    }
+
+    #[test]
+    fn rejects_a_claimed_score_the_run_doesnt_reach() {
+        let (replay, _) = play(&[]);
+        assert_eq!(
+            replay.verify(2),
+            Err(ReplayError::ScoreMismatch {
+                claimed: 2,
+                replayed: 1
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_turns_out_of_place() {
+        let heading = Simulation::new(GameRules::CLASSIC, SEED).heading();
+        let (mut replay, score) = play(&[]);
+
+        replay.turns =
+            vec![(3, heading.clockwise()), (2, heading.counter_clockwise())];
+        assert_eq!(replay.verify(score), Err(ReplayError::BadTurn(2)));
+
+        replay.turns =
+            vec![(2, heading.clockwise()), (2, heading.counter_clockwise())];
+        assert_eq!(replay.verify(score), Err(ReplayError::BadTurn(2)));
+
+        replay.turns = vec![(replay.ticks + 1, heading.clockwise())];
+        let past_the_end = replay.ticks + 1;
+        assert_eq!(
+            replay.verify(score),
+            Err(ReplayError::BadTurn(past_the_end))
+        );
+    }
+
+    #[test]
+    fn rejects_runs_that_end_elsewhere() {
+        let (replay, score) = play(&[]);
+
+        let mut longer = replay.clone();
+        longer.ticks += 1;
+        assert_eq!(
+            longer.verify(score),
+            Err(ReplayError::EndedEarly(replay.ticks))
+        );
+
+        let mut shorter = replay;
+        shorter.ticks -= 1;
+        assert_eq!(shorter.verify(score), Err(ReplayError::DidNotEnd));
+    }
+
+    #[test]
+    fn rejects_runs_too_long_to_check() {
+        let mut replay = Replay::new(SEED, GameRules::CLASSIC);
+        replay.ticks = MAX_REPLAY_TICKS + 1;
+        assert_eq!(
+            replay.verify(0),
+            Err(ReplayError::TooLong(MAX_REPLAY_TICKS + 1))
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! Scores and the boards they are kept on, the same for the game and the
+//! leaderboard server.
+
+use crate::game_mode::GameRules;
+
+/// Places shown at the top of the leaderboard screen
+pub const TOP_PLACES: usize = 10;
+
+/// Entries shown either side of the player's own place
+pub const NEIGHBOURS: usize = 2;
+
+/// Format [`Highscore`]s are written in. Version 2 added the game version
+/// and [`RunStats`]. Records without a version are version 1, and read as
+/// version 2 records with those missing.
+pub const HIGHSCORE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Highscore {
+    /// The [`HIGHSCORE_VERSION`] the record was written in
+    #[serde(default = "first_version")]
+    pub version: u32,
+    pub name: String,
+    pub score: u32,
+    /// How the snake was steered, scores from before this was recorded
+    /// count as absolute
+    #[serde(default)]
+    pub controls: crate::controls::ControlScheme,
+    /// The rules the run was played under, scores from before this was
+    /// recorded count as classic
+    #[serde(default)]
+    pub rules: GameRules,
+    /// Cells along each side of the board
+    #[serde(default = "default_board_size")]
+    pub board_size: i32,
+    /// The run itself, for the board to check the score against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<crate::replay::Replay>,
+    /// Added when the run is entered, for a board that checks signatures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<crate::signing::Signature>,
+    /// Seconds since the Unix epoch when the score was set, unknown for
+    /// scores from before this was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// The player profile that set the score, unknown for scores from
+    /// before profiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_id: Option<String>,
+    /// Version of the game the run was played on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<RunStats>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+fn default_board_size() -> i32 {
+    crate::sim::BOARD_CELLS
+}
+
+/// How a run went, worked out by playing its replay back
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RunStats {
+    /// Segments at the end, head included
+    pub length: u32,
+    pub ticks: u64,
+    /// Time played, not counting pauses
+    pub seconds: f32,
+    /// Top speed reached, in cells per second
+    pub max_speed: f32,
+    /// What ended the run, `None` if the snake filled the board
+    pub death: Option<crate::sim::DeathCause>,
+}
+
+impl Highscore {
+    /// A score in the current format with nothing else known about the run
+    pub fn new(name: &str, score: u32, rules: GameRules) -> Self {
+        Self {
+            version: HIGHSCORE_VERSION,
+            name: name.to_string(),
+            score,
+            controls: Default::default(),
+            rules,
+            board_size: crate::sim::BOARD_CELLS,
+            replay: None,
+            signature: None,
+            timestamp: None,
+            player_id: None,
+            game_version: None,
+            stats: None,
+        }
+    }
+
+    /// The name of the mode the run was played in, which picks its board
+    pub fn mode(&self) -> &'static str {
+        self.rules.name()
+    }
+
+    /// Whether both scores were set by the same known player in the same
+    /// mode
+    pub fn same_player(&self, other: &Highscore) -> bool {
+        self.player_id.is_some()
+            && self.player_id == other.player_id
+            && self.mode() == other.mode()
+    }
+}
+
+/// Adds `highscore` to `board`, which is sorted best first, after the
+/// scores it ties with. A known player keeps one entry per mode: a better
+/// score replaces theirs, anything else only renames it. Boards that take
+/// scores from others must keep player IDs to themselves, as holding one
+/// is all it takes to rename its entry.
+pub fn insert_best(board: &mut Vec<Highscore>, highscore: Highscore) {
+    if let Some(i) = board.iter().position(|s| s.same_player(&highscore)) {
+        if board[i].score >= highscore.score {
+            board[i].name = highscore.name;
+            return;
+        }
+        board.remove(i);
+    }
+    let at = board.partition_point(|s| s.score >= highscore.score);
+    board.insert(at, highscore);
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
+pub struct Highscores {
+    pub highscores: Vec<Highscore>,
+}
+
+/// Where a score places on a board and the entries around it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Standing {
+    pub score: u32,
+    /// The place the score takes, starting at 1. Ties place below the
+    /// scores already on the board.
+    pub rank: usize,
+    /// Scores on the board, not counting this one
+    pub total: usize,
+    /// The entries just above, best first
+    pub above: Vec<Highscore>,
+    /// The entries just below, best first
+    pub below: Vec<Highscore>,
+}
+
+impl Standing {
+    /// Where `score` would place on `board`, which is sorted best first,
+    /// with up to `around` entries either side
+    pub fn on(board: &[Highscore], score: u32, around: usize) -> Self {
+        let at = board.partition_point(|s| s.score >= score);
+        Self {
+            score,
+            rank: at + 1,
+            total: board.len(),
+            above: board[at.saturating_sub(around)..at].to_vec(),
+            below: board[at..(at + around).min(board.len())].to_vec(),
+        }
+    }
+
+    /// The entries around the score with their places, the score itself
+    /// as `None`
+    pub fn places(&self) -> Vec<(usize, Option<&Highscore>)> {
+        let first = self.rank - self.above.len();
+        let above = self.above.iter().map(Some);
+        let below = self.below.iter().map(Some);
+        above
+            .chain(std::iter::once(None))
+            .chain(below)
+            .enumerate()
+            .map(|(i, s)| (first + i, s))
+            .collect()
+    }
+}
+
+/// How far back the leaderboard browser looks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeRange {
+    #[default]
+    AllTime,
+    Today,
+    ThisWeek,
+    ThisMonth,
+}
+
+impl TimeRange {
+    pub const ALL: [Self; 4] =
+        [Self::AllTime, Self::Today, Self::ThisWeek, Self::ThisMonth];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeRange::AllTime => "All time",
+            TimeRange::Today => "Today",
+            TimeRange::ThisWeek => "This week",
+            TimeRange::ThisMonth => "This month",
+        }
+    }
+
+    /// The range's length in seconds, `None` for all time
+    pub fn seconds(&self) -> Option<u64> {
+        const DAY: u64 = 24 * 60 * 60;
+        match self {
+            TimeRange::AllTime => None,
+            TimeRange::Today => Some(DAY),
+            TimeRange::ThisWeek => Some(7 * DAY),
+            TimeRange::ThisMonth => Some(30 * DAY),
+        }
+    }
+}
+
+/// Scores per page in the leaderboard browser
+pub const PAGE_SIZE: usize = 20;
+
+/// One page of a board, filtered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardQuery {
+    pub mode: String,
+    /// Matching scores to skip
+    pub offset: usize,
+    pub limit: usize,
+    /// Only scores set within this many seconds
+    pub within: Option<u64>,
+    /// Only names containing this, ignoring case
+    pub name: String,
+}
+
+impl Default for BoardQuery {
+    fn default() -> Self {
+        Self {
+            mode: GameRules::CLASSIC.name().to_string(),
+            offset: 0,
+            limit: PAGE_SIZE,
+            within: None,
+            name: String::new(),
+        }
+    }
+}
+
+/// A score and its place on the board it came from
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RankedHighscore {
+    pub rank: usize,
+    #[serde(flatten)]
+    pub highscore: Highscore,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BoardPage {
+    /// Scores matching the query, on every page
+    pub total: usize,
+    pub entries: Vec<RankedHighscore>,
+}
+
+impl BoardQuery {
+    /// Runs the query over `board`, which may hold every mode's scores.
+    /// Places count among the scores of the time range, so a name search
+    /// still shows where each score stands.
+    pub fn apply(&self, board: &[Highscore], now: u64) -> BoardPage {
+        let mut scores: Vec<&Highscore> = board
+            .iter()
+            .filter(|s| s.mode() == self.mode)
+            .filter(|s| match (self.within, s.timestamp) {
+                (None, _) => true,
+                (Some(within), Some(at)) => now.saturating_sub(at) <= within,
+                (Some(_), None) => false,
+            })
+            .collect();
+        scores.sort_by_key(|s| std::cmp::Reverse(s.score));
+
+        let name = self.name.to_lowercase();
+        let matching: Vec<RankedHighscore> = scores
+            .into_iter()
+            .enumerate()
+            .filter(|(_, s)| s.name.to_lowercase().contains(&name))
+            .map(|(i, s)| RankedHighscore {
+                rank: i + 1,
+                highscore: s.clone(),
+            })
+            .collect();
+        BoardPage {
+            total: matching.len(),
+            entries: matching
+                .into_iter()
+                .skip(self.offset)
+                .take(self.limit)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_000_000;
+
+    /// A classic score set `ago` seconds before [`NOW`], or at an unknown
+    /// time
+    fn score(name: &str, score: u32, ago: Option<u64>) -> Highscore {
+        let mut highscore = Highscore::new(name, score, GameRules::CLASSIC);
+        highscore.timestamp = ago.map(|ago| NOW - ago);
+        highscore
+    }
+
+    #[test]
+    fn reads_scores_from_before_versions() {
+        let json = r#"{"name":"Ada","score":3}"#;
+        let highscore: Highscore = serde_json::from_str(json).unwrap();
+        assert_eq!(highscore.version, 1);
+        assert_eq!(highscore.name, "Ada");
+        assert_eq!(highscore.score, 3);
+        assert_eq!(highscore.rules, GameRules::CLASSIC);
+        assert_eq!(highscore.controls, Default::default());
+        assert_eq!(highscore.board_size, crate::sim::BOARD_CELLS);
+        assert!(highscore.replay.is_none());
+        assert!(highscore.signature.is_none());
+        assert!(highscore.timestamp.is_none());
+        assert!(highscore.player_id.is_none());
+        assert!(highscore.game_version.is_none());
+        assert!(highscore.stats.is_none());
+
+        // written back in the current format, without the missing fields
+        let mut current = Highscore::new("Ada", 3, GameRules::CLASSIC);
+        let written = serde_json::to_value(&current).unwrap();
+        assert_eq!(written["version"], HIGHSCORE_VERSION);
+        assert!(written.get("stats").is_none());
+        current.version = 1;
+        assert_eq!(highscore, current);
+    }
+
+    fn board() -> Vec<Highscore> {
+        let mut walls = score("Wally", 20, Some(1));
+        walls.rules = GameRules::WALLS;
+        vec![
+            score("Ada", 9, Some(10)),
+            walls,
+            score("Bob", 8, None),
+            score("Cy", 7, Some(100)),
+            score("Dee", 6, Some(5)),
+            score("Eddie", 5, Some(1)),
+        ]
+    }
+
+    fn places(page: &BoardPage) -> Vec<(usize, &str)> {
+        page.entries
+            .iter()
+            .map(|e| (e.rank, e.highscore.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn time_range_drops_old_and_undated_scores() {
+        let board = board();
+        let page = BoardQuery::default().apply(&board, NOW);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.entries[1].highscore.name, "Bob");
+
+        let query = BoardQuery {
+            within: Some(50),
+            ..Default::default()
+        };
+        let page = query.apply(&board, NOW);
+        assert_eq!(page.total, 3);
+        assert_eq!(places(&page), [(1, "Ada"), (2, "Dee"), (3, "Eddie")]);
+    }
+
+    #[test]
+    fn name_search_ignores_case_and_keeps_places_in_range() {
+        let board = board();
+        let query = BoardQuery {
+            name: "E".to_string(),
+            ..Default::default()
+        };
+        let page = query.apply(&board, NOW);
+        assert_eq!(places(&page), [(4, "Dee"), (5, "Eddie")]);
+
+        // places count among the scores of the range, not the whole board
+        let query = BoardQuery {
+            within: Some(50),
+            name: "dE".to_string(),
+            ..Default::default()
+        };
+        let page = query.apply(&board, NOW);
+        assert_eq!(page.total, 1);
+        assert_eq!(places(&page), [(2, "Dee")]);
+    }
+
+    #[test]
+    fn pages_skip_and_limit_but_count_every_match() {
+        let board = board();
+        let query = BoardQuery {
+            offset: 1,
+            limit: 2,
+            ..Default::default()
+        };
+        let page = query.apply(&board, NOW);
+        assert_eq!(page.total, 5);
+        assert_eq!(places(&page), [(2, "Bob"), (3, "Cy")]);
+
+        let query = BoardQuery {
+            offset: 5,
+            ..Default::default()
+        };
+        let page = query.apply(&board, NOW);
+        assert_eq!(page.total, 5);
+        assert!(page.entries.is_empty());
+
+        let query = BoardQuery {
+            mode: GameRules::WALLS.name().to_string(),
+            ..Default::default()
+        };
+        assert_eq!(places(&query.apply(&board, NOW)), [(1, "Wally")]);
+    }
+}
@@ -0,0 +1,137 @@
+//! Signed submissions. With a key configured, every score the game sends
+//! carries an HMAC-SHA256 over its name, score, mode, player, a timestamp
+//! and a one-off nonce, and a board holding the same key only accepts scores
+//! signed with it.
+//!
+//! The key comes from the `RUST_SNAKE_SIGNING_KEY` environment variable,
+//! read at runtime on native and baked in at build time everywhere.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use web_time::SystemTime;
+
+use crate::score::Highscore;
+
+/// Environment variable holding the signing key
+pub const KEY_ENV: &str = "RUST_SNAKE_SIGNING_KEY";
+
+/// How far a signature's timestamp may be from a board's clock, in
+/// seconds. Boards remember nonces for as long.
+pub const SIGNATURE_WINDOW: u64 = 10 * 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Signature {
+    /// Seconds since the Unix epoch when the score was signed
+    pub timestamp: u64,
+    pub nonce: u64,
+    /// Hex encoded HMAC-SHA256
+    pub mac: String,
+}
+
+/// The key to sign with, if one was configured
+pub fn configured_key() -> Option<Vec<u8>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(key) = std::env::var(KEY_ENV) {
+        return (!key.is_empty()).then(|| key.into_bytes());
+    }
+    option_env!("RUST_SNAKE_SIGNING_KEY")
+        .filter(|key| !key.is_empty())
+        .map(|key| key.as_bytes().to_vec())
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+fn mac(
+    key: &[u8],
+    highscore: &Highscore,
+    mode: &str,
+    timestamp: u64,
+    nonce: u64,
+) -> HmacSha256 {
+    // JSON keeps the fields apart whatever the name contains
+    let fields = (&highscore.name, highscore.score, mode, &highscore.player_id);
+    let message = serde_json::to_vec(&fields).expect("fields serialize");
+    let mut mac =
+        HmacSha256::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(&message);
+    mac.update(&timestamp.to_be_bytes());
+    mac.update(&nonce.to_be_bytes());
+    mac
+}
+
+/// Signs `highscore` as played in `mode`, now and with a fresh nonce
+pub fn sign(key: &[u8], highscore: &Highscore, mode: &str) -> Signature {
+    let timestamp = now();
+    let nonce = rand::random();
+    Signature {
+        timestamp,
+        nonce,
+        mac: hex::encode(
+            mac(key, highscore, mode, timestamp, nonce)
+                .finalize()
+                .into_bytes(),
+        ),
+    }
+}
+
+/// Whether `signature` is young enough to send as it is. Half the window
+/// leaves time for the trip and for clocks that disagree.
+pub fn is_fresh(signature: &Signature) -> bool {
+    now().abs_diff(signature.timestamp) < SIGNATURE_WINDOW / 2
+}
+
+/// Checks that `signature` was made with `key` for `highscore` in `mode`
+pub fn verify(
+    key: &[u8],
+    highscore: &Highscore,
+    mode: &str,
+    signature: &Signature,
+) -> bool {
+    let Ok(expected) = hex::decode(&signature.mac) else {
+        return false;
+    };
+    mac(key, highscore, mode, signature.timestamp, signature.nonce)
+        .verify_slice(&expected)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highscore() -> Highscore {
+        serde_json::from_str(r#"{"name": "Ada", "score": 3}"#).unwrap()
+    }
+
+    #[test]
+    fn signatures_verify_with_their_key_only() {
+        let highscore = highscore();
+        let signature = sign(b"key", &highscore, "classic");
+        assert!(verify(b"key", &highscore, "classic", &signature));
+        assert!(!verify(b"other", &highscore, "classic", &signature));
+        assert!(!verify(b"key", &highscore, "walls", &signature));
+
+        let mut changed = highscore.clone();
+        changed.score = 4;
+        assert!(!verify(b"key", &changed, "classic", &signature));
+        changed = highscore.clone();
+        changed.player_id = Some("someone".to_string());
+        assert!(!verify(b"key", &changed, "classic", &signature));
+    }
+
+    #[test]
+    fn signatures_go_stale() {
+        let mut signature = sign(b"key", &highscore(), "classic");
+        assert!(is_fresh(&signature));
+        signature.timestamp -= SIGNATURE_WINDOW / 2;
+        assert!(!is_fresh(&signature));
+    }
+}
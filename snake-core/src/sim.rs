@@ -0,0 +1,458 @@
+//! The rules of the game without any of the Bevy machinery, so that a run
+//! can be simulated headlessly (attract mode, AI, tooling) from a seed.
+
+use std::collections::VecDeque;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::game_mode::GameRules;
+use crate::snake::Direction;
+use crate::Position;
+
+/// Largest coordinate the head may occupy on either axis
+pub const BOARD_MAX: i32 = crate::WALL as i32;
+
+/// Number of cells along each axis of the board
+pub const BOARD_CELLS: i32 = BOARD_MAX + 1;
+
+/// Where every snake starts
+pub const START: Position = Position { x: 10, y: 10 };
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    /// The head left the board
+    OutOfBounds,
+    /// The head ran into the snake's own body
+    SelfBite,
+    /// The head ran into a wall spawned by the Walls mode
+    Wall,
+    /// The head ran into another snake in the arena
+    OtherSnake,
+}
+
+impl DeathCause {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OutOfBounds => "out_of_bounds",
+            Self::SelfBite => "self_bite",
+            Self::Wall => "wall",
+            Self::OtherSnake => "other_snake",
+        }
+    }
+
+    /// How the run ended, for people
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::OutOfBounds => "Left the board",
+            Self::SelfBite => "Bit itself",
+            Self::Wall => "Hit a wall",
+            Self::OtherSnake => "Hit another snake",
+        }
+    }
+}
+
+/// The cell the head moves to when travelling in `dir`
+pub fn advance(pos: Position, dir: Direction) -> Position {
+    match dir {
+        Direction::Up => Position::new(pos.x, pos.y + 1),
+        Direction::Right => Position::new(pos.x + 1, pos.y),
+        Direction::Down => Position::new(pos.x, pos.y - 1),
+        Direction::Left => Position::new(pos.x - 1, pos.y),
+    }
+}
+
+pub fn in_bounds(pos: Position) -> bool {
+    pos.x >= 0 && pos.x <= BOARD_MAX && pos.y >= 0 && pos.y <= BOARD_MAX
+}
+
+/// The cell behind the head of a freshly spawned snake
+pub fn start_tail(dir: Direction) -> Position {
+    advance(START, dir.opposite())
+}
+
+/// Turns buffered during a tick when none were given
+pub const DEFAULT_INPUT_DEPTH: usize = 3;
+
+/// Turns waiting to be made, one per tick. Each turn is checked against the
+/// one queued before it when it arrives, so taps made faster than the snake
+/// moves are kept in order instead of being dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputBuffer {
+    queue: VecDeque<Direction>,
+    depth: usize,
+}
+
+impl Default for InputBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_INPUT_DEPTH)
+    }
+}
+
+impl InputBuffer {
+    /// A buffer holding at most `depth` turns, at least one
+    pub fn new(depth: usize) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            depth: depth.max(1),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Changes how many turns are kept, dropping the newest if it shrinks
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth.max(1);
+        self.queue.truncate(self.depth);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Direction> {
+        self.queue.iter()
+    }
+
+    /// The way the snake will be heading once every queued turn is made
+    pub fn heading_after(&self, heading: Direction) -> Direction {
+        self.queue.back().copied().unwrap_or(heading)
+    }
+
+    /// Queues a turn, given the heading the snake currently has. Turns that
+    /// don't change direction, reversals and turns past the buffer's depth
+    /// are dropped, and `false` is returned for them.
+    pub fn push(&mut self, dir: Direction, heading: Direction) -> bool {
+        let last = self.heading_after(heading);
+        if dir == last || dir == last.opposite() || self.len() >= self.depth
+        {
+            return false;
+        }
+        self.queue.push_back(dir);
+        true
+    }
+
+    /// The turn to make this tick
+    pub fn pop(&mut self) -> Option<Direction> {
+        self.queue.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
+
+/// Applies a queued turn to the current heading, ignoring reversals
+pub fn turn(heading: Direction, input: Option<Direction>) -> Direction {
+    match input {
+        Some(dir) if dir != heading.opposite() => dir,
+        _ => heading,
+    }
+}
+
+/// Decides whether the head entering `head` is fatal. `body` holds the
+/// positions of every segment before the move.
+pub fn check_collision(
+    head: Position,
+    body: &[Position],
+    walls: &[Position],
+    rules: &GameRules,
+) -> Option<DeathCause> {
+    if !in_bounds(head) {
+        Some(DeathCause::OutOfBounds)
+    } else if body.contains(&head) {
+        Some(DeathCause::SelfBite)
+    } else if rules.do_collide_walls && walls.contains(&head) {
+        Some(DeathCause::Wall)
+    } else {
+        None
+    }
+}
+
+/// Tick rate after the score has been increased to `score`
+pub fn next_tick_rate(tick_rate: f32, score: u32) -> f32 {
+    if score.is_multiple_of(10) {
+        tick_rate + crate::BIG_TICK_INCREASE
+    } else {
+        tick_rate + crate::TICK_INCREASE
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepOutcome {
+    pub ate: bool,
+    pub death: Option<DeathCause>,
+}
+
+/// A single game of snake, advanced one tick at a time
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    rules: GameRules,
+    rng: StdRng,
+    snake: VecDeque<Position>,
+    heading: Direction,
+    food: Option<Position>,
+    walls: Vec<Position>,
+    wall_queue: Vec<Position>,
+    score: u32,
+    ticks: u64,
+    tick_rate: f32,
+    /// Time played so far, each tick lasting as long as it would on screen
+    seconds: f32,
+    death: Option<DeathCause>,
+}
+
+impl Simulation {
+    pub fn new(rules: GameRules, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let heading: Direction = rng.gen::<u8>().into();
+        let mut sim = Self {
+            rules,
+            rng,
+            snake: VecDeque::from([START, start_tail(heading)]),
+            heading,
+            food: None,
+            walls: Vec::new(),
+            wall_queue: Vec::new(),
+            score: 0,
+            ticks: 0,
+            tick_rate: crate::TICK_RATE,
+            seconds: 0.0,
+            death: None,
+        };
+        sim.spawn_food();
+        sim
+    }
+
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
+    }
+
+    pub fn head(&self) -> Position {
+        self.snake[0]
+    }
+
+    /// Every segment, head first
+    pub fn snake(&self) -> &VecDeque<Position> {
+        &self.snake
+    }
+
+    pub fn heading(&self) -> Direction {
+        self.heading
+    }
+
+    pub fn food(&self) -> Option<Position> {
+        self.food
+    }
+
+    pub fn walls(&self) -> &[Position] {
+        &self.walls
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn tick_rate(&self) -> f32 {
+        self.tick_rate
+    }
+
+    /// Seconds the game has been played for, not counting pauses
+    pub fn seconds(&self) -> f32 {
+        self.seconds
+    }
+
+    pub fn death(&self) -> Option<DeathCause> {
+        self.death
+    }
+
+    /// The snake has filled every cell food could be placed in
+    pub fn is_perfect(&self) -> bool {
+        self.death.is_none() && self.food.is_none()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.death.is_some() || self.food.is_none()
+    }
+
+    /// Would moving the head one cell in `dir` end the game?
+    pub fn is_fatal(&self, dir: Direction) -> bool {
+        let body = self.snake.iter().copied().collect::<Vec<_>>();
+        check_collision(
+            advance(self.head(), turn(self.heading, Some(dir))),
+            &body,
+            &self.walls,
+            &self.rules,
+        )
+        .is_some()
+    }
+
+    /// Advances the game by one tick, turning first if `input` is given
+    pub fn step(&mut self, input: Option<Direction>) -> StepOutcome {
+        if self.is_over() {
+            return StepOutcome {
+                ate: false,
+                death: self.death,
+            };
+        }
+
+        self.ticks += 1;
+        self.seconds += 1.0 / self.tick_rate;
+        self.heading = turn(self.heading, input);
+
+        let body = self.snake.iter().copied().collect::<Vec<_>>();
+        let head = advance(self.head(), self.heading);
+        self.snake.push_front(head);
+        let last_tail = self.snake.pop_back().unwrap();
+
+        if let Some(cause) =
+            check_collision(head, &body, &self.walls, &self.rules)
+        {
+            self.death = Some(cause);
+            return StepOutcome {
+                ate: false,
+                death: Some(cause),
+            };
+        }
+
+        let ate = self.food == Some(head);
+        if ate {
+            self.score += 1;
+            self.tick_rate = next_tick_rate(self.tick_rate, self.score);
+            self.snake.push_back(last_tail);
+            if self.rules.do_spawn_walls {
+                self.wall_queue.push(head);
+            }
+            self.spawn_food();
+        }
+
+        let snake = &self.snake;
+        let (blocked, free): (Vec<_>, Vec<_>) =
+            self.wall_queue.drain(..).partition(|pos| snake.contains(pos));
+        self.wall_queue = blocked;
+        self.walls.extend(free);
+
+        StepOutcome { ate, death: None }
+    }
+
+    fn spawn_food(&mut self) {
+        let snake = self.snake.iter().copied().collect::<Vec<_>>();
+        self.food = place_food(&mut self.rng, &snake);
+    }
+}
+
+/// Picks a cell for the next food that the snake isn't on, or `None` once
+/// the snake fills the board. The live game and [`Simulation`] both place
+/// food with this, so a run replays the same from its seed.
+pub fn place_food<R: Rng + ?Sized>(
+    rng: &mut R,
+    snake: &[Position],
+) -> Option<Position> {
+    let free_cells = (BOARD_MAX * BOARD_MAX) as usize;
+    let occupied = snake
+        .iter()
+        .filter(|p| p.x < BOARD_MAX && p.y < BOARD_MAX)
+        .count();
+    if occupied >= free_cells {
+        return None;
+    }
+
+    let mut food = crate::food::generate_food_coords_with(rng);
+    while snake.contains(&food) {
+        food = crate::food::generate_food_coords_with(rng);
+    }
+    Some(food)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_mode::GameRules;
+
+    #[test]
+    fn buffer_plays_turns_in_order() {
+        let mut buffer = InputBuffer::new(3);
+        assert!(buffer.push(Direction::Left, Direction::Up));
+        assert!(buffer.push(Direction::Down, Direction::Up));
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.heading_after(Direction::Up), Direction::Down);
+
+        assert_eq!(buffer.pop(), Some(Direction::Left));
+        assert_eq!(buffer.pop(), Some(Direction::Down));
+        assert_eq!(buffer.pop(), None);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn buffer_ignores_reversals_and_repeats() {
+        let mut buffer = InputBuffer::new(3);
+        assert!(!buffer.push(Direction::Down, Direction::Up));
+        assert!(!buffer.push(Direction::Up, Direction::Up));
+        assert!(buffer.push(Direction::Right, Direction::Up));
+        // checked against the last queued turn, not the current heading
+        assert!(!buffer.push(Direction::Left, Direction::Up));
+        assert!(!buffer.push(Direction::Right, Direction::Up));
+        assert_eq!(
+            buffer.iter().copied().collect::<Vec<_>>(),
+            [Direction::Right]
+        );
+    }
+
+    #[test]
+    fn buffer_drops_turns_past_its_depth() {
+        let mut buffer = InputBuffer::new(2);
+        assert!(buffer.push(Direction::Left, Direction::Up));
+        assert!(buffer.push(Direction::Up, Direction::Up));
+        assert!(!buffer.push(Direction::Right, Direction::Up));
+        assert_eq!(buffer.len(), 2);
+
+        buffer.set_depth(1);
+        assert_eq!(buffer.depth(), 1);
+        assert_eq!(buffer.pop(), Some(Direction::Left));
+        assert_eq!(buffer.pop(), None);
+
+        assert_eq!(InputBuffer::new(0).depth(), 1);
+    }
+
+    #[test]
+    fn quick_u_turn_takes_consecutive_ticks() {
+        let mut sim = Simulation::new(GameRules::CLASSIC, 0);
+        let heading = sim.heading();
+        let mut buffer = InputBuffer::default();
+
+        // both turns arrive before the next tick
+        let side = heading.clockwise();
+        assert!(buffer.push(side, sim.heading()));
+        assert!(buffer.push(heading.opposite(), sim.heading()));
+
+        let start = sim.head();
+        sim.step(buffer.pop());
+        assert_eq!(sim.heading(), side);
+        assert_eq!(sim.head(), advance(start, side));
+        sim.step(buffer.pop());
+        assert_eq!(sim.heading(), heading.opposite());
+        let back = advance(advance(start, side), heading.opposite());
+        assert_eq!(sim.head(), back);
+        assert!(!sim.is_over());
+    }
+}
@@ -0,0 +1,64 @@
+//! Which way the snake is heading.
+
+#[derive(
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Debug,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    #[default]
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] =
+        [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+        }
+    }
+
+    pub fn clockwise(self) -> Self {
+        match self {
+            Self::Up => Self::Right,
+            Self::Right => Self::Down,
+            Self::Down => Self::Left,
+            Self::Left => Self::Up,
+        }
+    }
+
+    pub fn counter_clockwise(self) -> Self {
+        self.clockwise().opposite()
+    }
+}
+
+impl From<u8> for Direction {
+    fn from(val: u8) -> Self {
+        let bound = val % 4;
+        if bound == 0 {
+            Direction::Up
+        } else if bound == 1 {
+            Direction::Right
+        } else if bound == 2 {
+            Direction::Down
+        } else {
+            Direction::Left
+        }
+    }
+}
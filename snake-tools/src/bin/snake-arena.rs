@@ -0,0 +1,382 @@
+//! A headless arena where bots connected over local TCP play each other.
+//!
+//! Bots connect and speak line based JSON. After `{"type":"hello",
+//! "name":"..."}` the server answers `{"type":"welcome","id":0}`, and once
+//! everyone is in the lobby it sends `{"type":"lobby","players":[...]}`.
+//! Each bot then sends `{"type":"ready"}`. Every match opens with
+//! `{"type":"start",...}`, followed by one `{"type":"tick",...}` per tick
+//! that bots answer with `{"type":"move","direction":"left"}`, and closes
+//! with `{"type":"match_end",...}`. The final standings are sent as
+//! `{"type":"tournament_end",...}` and written to the output file.
+//!
+//! `cargo run -p snake-tools --bin snake-arena -- --players 4 --schedule swiss`
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use clap::{Parser, ValueEnum};
+use snake_core::arena::{
+    ArenaBoard, Schedule, Standing, Tournament, MAX_SNAKES,
+};
+use snake_core::game_mode::GameRules;
+use snake_core::sim::BOARD_CELLS;
+use snake_core::snake::Direction;
+use snake_core::Position;
+
+/// Time a bot has to say hello, and later to say it's ready
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ScheduleKind {
+    RoundRobin,
+    Swiss,
+}
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Address to listen on
+    #[clap(long, default_value = "127.0.0.1:7878")]
+    bind: String,
+    /// Bots to wait for before the tournament starts
+    #[clap(long, default_value_t = 2)]
+    players: usize,
+    /// Snakes in each match, from 2 to 36
+    #[clap(long, default_value_t = 2)]
+    match_size: usize,
+    #[clap(long, value_enum, default_value_t = ScheduleKind::RoundRobin)]
+    schedule: ScheduleKind,
+    /// Rounds to play with the swiss schedule
+    #[clap(long, default_value_t = 3)]
+    rounds: usize,
+    /// `classic` or `walls`
+    #[clap(long, default_value = "classic")]
+    mode: String,
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// Time each bot has to answer a tick
+    #[clap(long, default_value_t = 100)]
+    budget_ms: u64,
+    /// Ticks after which a match is stopped and ranked as it stands
+    #[clap(long, default_value_t = 5000)]
+    max_ticks: u64,
+    /// Where to write the final standings
+    #[clap(long, default_value = "standings.json")]
+    output: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Hello { name: String },
+    Ready,
+    Move { direction: String },
+}
+
+#[derive(Debug, serde::Serialize)]
+struct SnakeState {
+    id: usize,
+    alive: bool,
+    score: u32,
+    body: Vec<[i32; 2]>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    Welcome {
+        id: usize,
+    },
+    Lobby {
+        players: Vec<&'a str>,
+    },
+    Start {
+        round: usize,
+        you: usize,
+        players: &'a [usize],
+    },
+    Tick {
+        tick: u64,
+        width: i32,
+        height: i32,
+        snakes: Vec<SnakeState>,
+        food: Vec<[i32; 2]>,
+        walls: Vec<[i32; 2]>,
+    },
+    MatchEnd {
+        placements: Vec<usize>,
+    },
+    TournamentEnd {
+        standings: &'a [Standing],
+    },
+}
+
+struct Client {
+    name: String,
+    stream: TcpStream,
+    messages: Receiver<ClientMessage>,
+    /// The connection closed, so the bot is withdrawn after this match
+    gone: bool,
+}
+
+impl Client {
+    fn send(&mut self, message: &ServerMessage) {
+        let line = serde_json::to_string(message).expect("message serializes");
+        // a bot that has gone away simply stops steering
+        let _ = writeln!(self.stream, "{}", line);
+    }
+
+    /// Waits up to `timeout` for the next message, noting a closed
+    /// connection
+    fn recv(&mut self, timeout: Duration) -> Option<ClientMessage> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(message) => Some(message),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                self.gone = true;
+                None
+            }
+        }
+    }
+}
+
+fn cell(pos: &Position) -> [i32; 2] {
+    [pos.x, pos.y]
+}
+
+fn parse_direction(direction: &str) -> Option<Direction> {
+    match direction {
+        "up" => Some(Direction::Up),
+        "down" => Some(Direction::Down),
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+/// Accepts connections until `players` bots have said hello
+fn lobby(
+    listener: &TcpListener,
+    players: usize,
+) -> std::io::Result<Vec<Client>> {
+    let mut clients = Vec::new();
+    while clients.len() < players {
+        let (stream, addr) = listener.accept()?;
+        let reader = stream.try_clone()?;
+        let (tx, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else { break };
+                match serde_json::from_str::<ClientMessage>(&line) {
+                    Ok(message) => {
+                        if tx.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => eprintln!("arena => bad message: {}", e),
+                }
+            }
+        });
+
+        match messages.recv_timeout(HANDSHAKE_TIMEOUT) {
+            Ok(ClientMessage::Hello { name }) => {
+                println!("arena => {} joined from {}", name, addr);
+                let mut client = Client {
+                    name,
+                    stream,
+                    messages,
+                    gone: false,
+                };
+                client.send(&ServerMessage::Welcome { id: clients.len() });
+                clients.push(client);
+            }
+            _ => eprintln!("arena => {} didn't say hello, dropping", addr),
+        }
+    }
+
+    let names = clients.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+    let players = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+    for client in clients.iter_mut() {
+        client.send(&ServerMessage::Lobby {
+            players: players.clone(),
+        });
+    }
+    for client in clients.iter_mut() {
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match client.recv(timeout) {
+                Some(ClientMessage::Ready) => break,
+                Some(_) => continue,
+                None => {
+                    eprintln!("arena => {} wasn't ready in time", client.name);
+                    client.gone = true;
+                    break;
+                }
+            }
+        }
+    }
+    Ok(clients)
+}
+
+fn play_match(
+    clients: &mut [Client],
+    players: &[usize],
+    round: usize,
+    cli: &Cli,
+    rules: GameRules,
+    seed: u64,
+) -> ArenaBoard {
+    let mut board = ArenaBoard::new(players, rules, seed);
+    for id in players {
+        clients[*id].send(&ServerMessage::Start {
+            round,
+            you: *id,
+            players,
+        });
+    }
+
+    let budget = Duration::from_millis(cli.budget_ms);
+    while !board.is_over() && board.ticks() < cli.max_ticks {
+        let tick = ServerMessage::Tick {
+            tick: board.ticks(),
+            width: BOARD_CELLS,
+            height: BOARD_CELLS,
+            snakes: board
+                .snakes()
+                .iter()
+                .map(|s| SnakeState {
+                    id: s.id,
+                    alive: s.is_alive(),
+                    score: s.score,
+                    body: s.body.iter().map(cell).collect(),
+                })
+                .collect(),
+            food: board.food().iter().map(cell).collect(),
+            walls: board.walls().iter().map(cell).collect(),
+        };
+        for id in players {
+            // drop answers to earlier ticks
+            while clients[*id].messages.try_recv().is_ok() {}
+            clients[*id].send(&tick);
+        }
+
+        let deadline = Instant::now() + budget;
+        let inputs = players
+            .iter()
+            .map(|id| {
+                let timeout =
+                    deadline.saturating_duration_since(Instant::now());
+                match clients[*id].recv(timeout) {
+                    Some(ClientMessage::Move { direction }) => {
+                        parse_direction(&direction)
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        board.step(&inputs);
+    }
+
+    let placements = board.placements();
+    for id in players {
+        clients[*id].send(&ServerMessage::MatchEnd {
+            placements: placements.clone(),
+        });
+    }
+    board
+}
+
+/// Stops scheduling bots whose connection has closed
+fn withdraw_gone(clients: &[Client], tournament: &mut Tournament) {
+    for (id, client) in clients.iter().enumerate() {
+        if client.gone && tournament.is_playing(id) {
+            println!("arena => {} left, withdrawing", client.name);
+            tournament.withdraw(id);
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let Some(rules) = GameRules::from_name(&cli.mode) else {
+        eprintln!("unknown mode {:?}, expected classic or walls", cli.mode);
+        std::process::exit(1);
+    };
+    if !(2..=MAX_SNAKES).contains(&cli.match_size) {
+        eprintln!("--match-size must be between 2 and {}", MAX_SNAKES);
+        std::process::exit(1);
+    }
+    let schedule = match cli.schedule {
+        ScheduleKind::RoundRobin => Schedule::RoundRobin,
+        ScheduleKind::Swiss => Schedule::Swiss { rounds: cli.rounds },
+    };
+
+    let listener = match TcpListener::bind(&cli.bind) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("couldn't listen on {}: {}", cli.bind, e);
+            std::process::exit(1);
+        }
+    };
+    println!("arena => waiting for {} bots on {}", cli.players, cli.bind);
+    let mut clients = match lobby(&listener, cli.players) {
+        Ok(clients) => clients,
+        Err(e) => {
+            eprintln!("arena => lobby failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let names = clients.iter().map(|c| c.name.clone()).collect();
+    let mut tournament = Tournament::new(names, cli.match_size);
+    withdraw_gone(&clients, &mut tournament);
+    let mut seed = cli.seed;
+    let rounds = match schedule {
+        Schedule::RoundRobin => 1,
+        Schedule::Swiss { rounds } => rounds,
+    };
+    for round in 0..rounds {
+        let matches = match schedule {
+            Schedule::RoundRobin => tournament.round_robin(),
+            Schedule::Swiss { .. } => tournament.swiss_round(),
+        };
+        for players in matches {
+            // a round robin is drawn up front, so drop anyone who has left
+            // since
+            let players = players
+                .into_iter()
+                .filter(|id| tournament.is_playing(*id))
+                .collect::<Vec<_>>();
+            if players.len() < 2 {
+                continue;
+            }
+            let board =
+                play_match(&mut clients, &players, round, &cli, rules, seed);
+            println!(
+                "arena => round {} {:?} finished {:?}",
+                round,
+                players,
+                board.placements()
+            );
+            tournament.record(&board);
+            withdraw_gone(&clients, &mut tournament);
+            seed += 1;
+        }
+    }
+
+    let standings = tournament.ranking();
+    for client in clients.iter_mut() {
+        client.send(&ServerMessage::TournamentEnd {
+            standings: &standings,
+        });
+    }
+    let json = serde_json::to_string_pretty(&standings)
+        .expect("standings should serialize");
+    if let Err(e) = std::fs::write(&cli.output, &json) {
+        eprintln!("couldn't write {}: {}", cli.output, e);
+        std::process::exit(1);
+    }
+    println!("{}", json);
+}
@@ -0,0 +1,82 @@
+//! Runs headless games for each AI strategy and prints a summary.
+//!
+//! `cargo run -p snake-tools --bin snake-bench -- --games 1000 --strategy pathfinder`
+
+use clap::{Parser, ValueEnum};
+use snake_core::ai::{strategy_by_name, STRATEGY_NAMES};
+use snake_core::bench::{self, Summary};
+use snake_core::game_mode::GameRules;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Games to play per strategy
+    #[clap(long, default_value_t = 1000)]
+    games: u64,
+    /// Seed of the first game, later games count up from it, wrapping
+    /// around after `u64::MAX`
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// `classic` or `walls`
+    #[clap(long, default_value = "classic")]
+    mode: String,
+    /// Strategies to run, all of them if none are given
+    #[clap(long = "strategy")]
+    strategies: Vec<String>,
+    /// Ticks without eating before a game is abandoned
+    #[clap(long, default_value_t = bench::DEFAULT_STALL_TICKS)]
+    stall_ticks: u64,
+    #[clap(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let Some(rules) = GameRules::from_name(&cli.mode) else {
+        eprintln!("unknown mode {:?}, expected classic or walls", cli.mode);
+        std::process::exit(1);
+    };
+    let strategies = if cli.strategies.is_empty() {
+        STRATEGY_NAMES.iter().map(|s| s.to_string()).collect()
+    } else {
+        cli.strategies.clone()
+    };
+
+    let mut summaries = Vec::new();
+    for name in strategies.iter() {
+        let reports = (0..cli.games)
+            .map(|i| cli.seed.wrapping_add(i))
+            .map(|seed| {
+                let Some(mut strategy) = strategy_by_name(name, seed) else {
+                    eprintln!(
+                        "unknown strategy {:?}, expected one of {:?}",
+                        name, STRATEGY_NAMES
+                    );
+                    std::process::exit(1);
+                };
+                bench::play(strategy.as_mut(), rules, seed, cli.stall_ticks)
+            })
+            .collect::<Vec<_>>();
+        summaries.push(Summary::new(name, &rules, &reports));
+    }
+
+    match cli.format {
+        Format::Csv => {
+            println!("{}", bench::csv_header());
+            for summary in summaries.iter() {
+                println!("{}", summary.csv_row());
+            }
+        }
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&summaries)
+                .expect("summaries should serialize");
+            println!("{}", json);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! The demo game played by the computer behind the main menu. It runs on a
+//! [`Simulation`] of its own, so it never touches the score or leaderboard.
+
+use bevy::prelude::*;
+use rand::random;
+
+use crate::ai::{Pathfinder, Strategy};
+use crate::game_mode::GameRules;
+use crate::sim::Simulation;
+
+/// Seconds without input before the demo starts playing again
+pub const ATTRACT_IDLE_SECS: f32 = 10.;
+
+#[derive(Component)]
+pub struct AttractPiece;
+
+#[derive(Resource)]
+pub struct AttractMode {
+    sim: Simulation,
+    strategy: Pathfinder,
+    timer: Timer,
+    idle: Timer,
+    runs: u64,
+    active: bool,
+}
+
+impl AttractMode {
+    fn restart(&mut self) {
+        self.runs += 1;
+        // alternate between the classic and walls rules
+        let rules = if self.runs.is_multiple_of(2) {
+            GameRules::WALLS
+        } else {
+            GameRules::CLASSIC
+        };
+        self.sim = Simulation::new(rules, random());
+        self.timer = tick_timer(&self.sim);
+    }
+}
+
+impl Default for AttractMode {
+    fn default() -> Self {
+        let sim = Simulation::new(GameRules::default(), random());
+        Self {
+            timer: tick_timer(&sim),
+            sim,
+            strategy: Pathfinder,
+            idle: Timer::from_seconds(ATTRACT_IDLE_SECS, TimerMode::Once),
+            runs: 0,
+            active: true,
+        }
+    }
+}
+
+fn tick_timer(sim: &Simulation) -> Timer {
+    Timer::from_seconds(1. / sim.tick_rate(), TimerMode::Repeating)
+}
+
+/// Stops the demo as soon as the player does anything
+pub fn attract_input(
+    mut commands: Commands,
+    mut attract: ResMut<AttractMode>,
+    actions: Res<crate::input::ActionState>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    pieces: Query<Entity, With<AttractPiece>>,
+) {
+    if !actions.any_just_pressed()
+        && mouse_input.get_just_pressed().next().is_none()
+    {
+        return;
+    }
+
+    attract.active = false;
+    attract.idle.reset();
+    for ent in pieces.iter() {
+        commands.entity(ent).despawn();
+    }
+}
+
+pub fn run_attract(
+    mut commands: Commands,
+    mut attract: ResMut<AttractMode>,
+    time: Res<Time>,
+    pieces: Query<Entity, With<AttractPiece>>,
+) {
+    let attract = &mut *attract;
+    if !attract.active {
+        if attract.idle.tick(time.delta()).just_finished() {
+            attract.active = true;
+            attract.restart();
+        }
+        return;
+    }
+
+    if !attract.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let dir = attract.strategy.next_direction(&attract.sim);
+    if attract.sim.step(dir).ate {
+        attract.timer = tick_timer(&attract.sim);
+    }
+    if attract.sim.is_over() {
+        attract.restart();
+    }
+
+    for ent in pieces.iter() {
+        commands.entity(ent).despawn();
+    }
+    let sim = &attract.sim;
+    let head_color = Color::rgba(125., 125., 175., 0.4);
+    let segment_color = Color::rgba(75., 75., 75., 0.4);
+    let food_color = Color::rgba(175., 0., 0., 0.4);
+    let wall_color = Color::GRAY.with_a(0.4);
+
+    let segments = sim.snake().iter().enumerate().map(|(i, pos)| {
+        let color = if i == 0 { head_color } else { segment_color };
+        (*pos, color)
+    });
+    let food = sim.food().map(|pos| (pos, food_color));
+    let walls = sim.walls().iter().map(|pos| (*pos, wall_color));
+    for (pos, color) in segments.chain(food).chain(walls) {
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                ..default()
+            })
+            .insert(AttractPiece)
+            .insert(pos)
+            .insert(crate::Size::square(crate::BLOCK_SIZE));
+    }
+}
+
+/// Removes the demo when leaving the main menu
+pub fn clear_attract(
+    mut commands: Commands,
+    mut attract: ResMut<AttractMode>,
+    pieces: Query<Entity, With<AttractPiece>>,
+) {
+    attract.active = false;
+    for ent in pieces.iter() {
+        commands.entity(ent).despawn();
+    }
+}
@@ -0,0 +1,221 @@
+//! Lets an external program play the game in a window, speaking the
+//! protocol described in [`snake_core::bot`], and reads the `--bot`
+//! command line options.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use crate::game_mode::{GameRules, Wall};
+use crate::snake::{InputQueue, SnakeHead, SnakeSegments};
+use crate::Position;
+
+pub use snake_core::bot::*;
+
+/// Drives the snake from a bot in place of [`crate::snake::control_snake`]
+#[derive(Resource)]
+pub struct BotController {
+    bot: Mutex<BotProcess>,
+    tick: u64,
+    asked_at: Option<Instant>,
+}
+
+impl BotController {
+    pub fn new(bot: BotProcess) -> Self {
+        Self {
+            bot: Mutex::new(bot),
+            tick: 0,
+            asked_at: None,
+        }
+    }
+}
+
+/// Sends the board once per tick and feeds the answer into the input queue
+pub fn bot_control(
+    mut controller: ResMut<BotController>,
+    timer: Res<crate::TickTimer>,
+    mut input_queue: ResMut<InputQueue>,
+    segments: Res<SnakeSegments>,
+    heads: Query<&SnakeHead>,
+    positions: Query<&Position>,
+    food: Query<&Position, With<crate::food::Food>>,
+    walls: Query<&Position, With<Wall>>,
+    score: Res<crate::score::Score>,
+    game_rules: Res<GameRules>,
+) {
+    let controller = &mut *controller;
+    let Some(head) = heads.iter().next() else {
+        return;
+    };
+    let bot = controller.bot.get_mut().unwrap();
+
+    // the timer was ticked by the previous frame's update_snake
+    if controller.asked_at.is_some() && timer.0.just_finished() {
+        controller.tick += 1;
+    }
+    if controller.asked_at.is_none() || timer.0.just_finished() {
+        let snake = segments
+            .iter()
+            .filter_map(|e| positions.get(*e).ok().copied())
+            .collect::<Vec<_>>();
+        let walls = walls.iter().copied().collect::<Vec<_>>();
+        bot.send(&BotMessage::tick(
+            controller.tick,
+            head.rot,
+            &snake,
+            food.iter().next().copied(),
+            &walls,
+            score.0,
+            &game_rules,
+        ));
+        controller.asked_at = Some(Instant::now());
+    }
+
+    let within_budget = controller
+        .asked_at
+        .is_some_and(|asked_at| asked_at.elapsed() <= bot.budget());
+    if let Ok(Some(dir)) = bot.try_receive() {
+        if within_budget {
+            input_queue.0.clear();
+            input_queue.0.push(dir, head.rot);
+        }
+    }
+}
+
+/// Starts a new conversation with the bot when a game starts
+pub fn reset_bot(mut controller: ResMut<BotController>) {
+    controller.tick = 0;
+    controller.asked_at = None;
+}
+
+/// Lets the bot know its game is over
+pub fn bot_game_over(
+    mut controller: ResMut<BotController>,
+    mut reader: EventReader<crate::GameOverEvent>,
+    score: Res<crate::score::Score>,
+) {
+    if reader.read().next().is_some() {
+        let bot = controller.bot.get_mut().unwrap();
+        bot.send(&BotMessage::End { score: score.0 });
+    }
+}
+
+/// Command line options for running a bot, `--bot <command>` enables it
+#[derive(Debug, Clone)]
+pub struct BotArgs {
+    pub command: String,
+    pub headless: bool,
+    pub seed: u64,
+    pub rules: GameRules,
+    pub budget: Duration,
+}
+
+/// The `--bot` options on the game's command line, see [`crate::cli`]
+#[derive(Debug, clap::Args)]
+pub struct BotCli {
+    /// Program that steers the snake, split on whitespace into a program
+    /// and arguments
+    #[arg(long = "bot", value_name = "COMMAND")]
+    command: Option<String>,
+    /// Play one game without a window and print how it went
+    #[arg(long, requires = "command")]
+    headless: bool,
+    /// Seed of a headless game, random if not given
+    #[arg(long, requires = "headless")]
+    seed: Option<u64>,
+    /// Mode of a headless game, `classic` or `walls`
+    #[arg(
+        long = "mode",
+        default_value = "classic",
+        value_parser = parse_mode,
+        requires = "headless"
+    )]
+    rules: GameRules,
+    /// Time the bot has to answer each tick
+    #[arg(long, default_value_t = DEFAULT_BUDGET.as_millis() as u64)]
+    budget_ms: u64,
+}
+
+fn parse_mode(mode: &str) -> Result<GameRules, String> {
+    GameRules::from_name(mode)
+        .ok_or_else(|| "expected classic or walls".to_string())
+}
+
+impl BotCli {
+    /// The bot asked for with `--bot <command> [--headless] [--seed <n>]
+    /// [--mode <mode>] [--budget-ms <ms>]`, `None` without `--bot`. The
+    /// seed and mode only apply to headless games and are refused without
+    /// `--headless`, with a window they come from the menu.
+    pub fn into_args(self) -> Option<BotArgs> {
+        self.command.map(|command| BotArgs {
+            command,
+            headless: self.headless,
+            seed: self.seed.unwrap_or_else(rand::random),
+            rules: self.rules,
+            budget: Duration::from_millis(self.budget_ms),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<BotArgs>, clap::Error> {
+        let args = args.iter().map(|arg| arg.to_string());
+        crate::cli::Args::parse_from(args, None).map(|args| args.bot)
+    }
+
+    #[test]
+    fn no_bot_without_bot_flag() {
+        assert!(parse(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn seed_and_mode_need_headless() {
+        assert!(parse(&["--seed", "4"]).is_err());
+        assert!(parse(&["--bot", "bot", "--seed", "4"]).is_err());
+        assert!(parse(&["--bot", "bot", "--mode", "walls"]).is_err());
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let args = parse(&[
+            "--bot",
+            "python3 bot.py",
+            "--headless",
+            "--seed",
+            "7",
+            "--mode",
+            "walls",
+            "--budget-ms",
+            "20",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.command, "python3 bot.py");
+        assert!(args.headless);
+        assert_eq!(args.seed, 7);
+        assert_eq!(args.rules, GameRules::WALLS);
+        assert_eq!(args.budget, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn defaults() {
+        let args = parse(&["--bot", "bot"]).unwrap().unwrap();
+        assert!(!args.headless);
+        assert_eq!(args.rules, GameRules::CLASSIC);
+        assert_eq!(args.budget, DEFAULT_BUDGET);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&["--headless"]).is_err());
+        let headless = ["--bot", "bot", "--headless"];
+        assert!(parse(&[&headless[..], &["--mode", "maze"]].concat()).is_err());
+        assert!(parse(&[&headless[..], &["--seed", "-1"]].concat()).is_err());
+        assert!(parse(&["--bot"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+    }
+}
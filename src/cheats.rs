@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 
-use crate::{snake::TickAccum, ResetEvent, BIG_TICK_INCREASE, TICK_INCREASE, TICK_RATE};
+use crate::{
+    input::{Action, ActionState},
+    snake::TickAccum,
+    ResetEvent, BIG_TICK_INCREASE, TICK_INCREASE, TICK_RATE,
+};
 
 #[derive(Resource)]
 pub struct ScoreBlocker(pub u32);
@@ -9,10 +13,13 @@ pub fn quick_speed(
     mut tick_timer: ResMut<crate::TickTimer>,
     score: ResMut<crate::score::Score>,
     mut tick_accum: ResMut<TickAccum>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     mut score_blocker: ResMut<ScoreBlocker>,
 ) {
-    if keyboard_input.pressed(KeyCode::KeyP) && score.0 == 0 && score_blocker.0 == 0 {
+    if actions.pressed(Action::QuickSpeed)
+        && score.0 == 0
+        && score_blocker.0 == 0
+    {
         tick_accum.0 =
             TICK_RATE + 27.0 * TICK_INCREASE + 3.0 * BIG_TICK_INCREASE;
         tick_timer.0 =
@@ -22,10 +29,10 @@ pub fn quick_speed(
 }
 
 pub fn quick_reset(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     mut reset_writer: EventWriter<ResetEvent>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Backslash) {
+    if actions.just_pressed(Action::Restart) {
         reset_writer.send(ResetEvent);
     }
 }
@@ -0,0 +1,150 @@
+//! The game's command line on native builds. The web build reads its
+//! leaderboard from the page instead.
+
+use std::path::PathBuf;
+
+use clap::CommandFactory;
+
+use crate::leaderboard::{LeaderboardConfig, URL_ENV};
+
+#[derive(Debug, clap::Parser)]
+#[command(name = "rust-snake")]
+struct Cli {
+    /// Leaderboard server to use, `off` for none or `memory` for a board
+    /// that only lasts the session. Read from RUST_SNAKE_LEADERBOARD when
+    /// no leaderboard option is given.
+    #[arg(
+        long,
+        value_name = "URL",
+        value_parser = LeaderboardConfig::from_value,
+        group = "leaderboard"
+    )]
+    leaderboard_url: Option<LeaderboardConfig>,
+    /// Keep the leaderboard in this JSON file
+    #[arg(long, value_name = "PATH", group = "leaderboard")]
+    leaderboard_file: Option<PathBuf>,
+    /// Only keep scores on this device
+    #[arg(long, group = "leaderboard")]
+    offline: bool,
+    #[cfg(feature = "tools")]
+    #[command(flatten)]
+    bot: crate::bot::BotCli,
+}
+
+/// Everything the command line asked for
+#[derive(Debug)]
+pub struct Args {
+    pub leaderboard: LeaderboardConfig,
+    /// The bot to play with, if `--bot` was given
+    #[cfg(feature = "tools")]
+    pub bot: Option<crate::bot::BotArgs>,
+}
+
+impl Args {
+    /// Parses the arguments after the program name, falling back to
+    /// [`URL_ENV`] for the leaderboard
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, clap::Error> {
+        Self::parse_from(args, std::env::var(URL_ENV).ok())
+    }
+
+    /// [`Args::parse`] with the environment's leaderboard URL given
+    pub(crate) fn parse_from(
+        args: impl IntoIterator<Item = String>,
+        env_url: Option<String>,
+    ) -> Result<Self, clap::Error> {
+        use clap::Parser;
+        let program = std::iter::once("rust-snake".to_string());
+        let cli = Cli::try_parse_from(program.chain(args))?;
+
+        let leaderboard = if let Some(config) = cli.leaderboard_url {
+            config
+        } else if let Some(path) = cli.leaderboard_file {
+            LeaderboardConfig::File(path)
+        } else if cli.offline {
+            LeaderboardConfig::Offline
+        } else if let Some(url) = env_url {
+            LeaderboardConfig::from_value(&url).map_err(|e| {
+                Cli::command().error(
+                    clap::error::ErrorKind::ValueValidation,
+                    format!("{}: {}", URL_ENV, e),
+                )
+            })?
+        } else {
+            LeaderboardConfig::default()
+        };
+
+        Ok(Self {
+            leaderboard,
+            #[cfg(feature = "tools")]
+            bot: cli.bot.into_args(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(
+        args: &[&str],
+        env_url: Option<&str>,
+    ) -> Result<LeaderboardConfig, clap::Error> {
+        let args = args.iter().map(|arg| arg.to_string());
+        Args::parse_from(args, env_url.map(String::from))
+            .map(|args| args.leaderboard)
+    }
+
+    #[test]
+    fn leaderboard_flags_win_over_the_environment() {
+        let env = Some("http://env.example");
+        assert_eq!(
+            parse(&[], env).unwrap(),
+            LeaderboardConfig::online("http://env.example")
+        );
+        assert_eq!(
+            parse(&["--leaderboard-url", "http://flag.example/"], env).unwrap(),
+            LeaderboardConfig::online("http://flag.example")
+        );
+        assert_eq!(
+            parse(&["--leaderboard-file", "scores.json"], env).unwrap(),
+            LeaderboardConfig::File("scores.json".into())
+        );
+        assert_eq!(
+            parse(&["--offline"], env).unwrap(),
+            LeaderboardConfig::Offline
+        );
+        assert_eq!(
+            parse(&["--leaderboard-url", "memory"], None).unwrap(),
+            LeaderboardConfig::Memory
+        );
+        assert_eq!(parse(&[], None).unwrap(), LeaderboardConfig::default());
+    }
+
+    #[test]
+    fn empty_urls_are_refused() {
+        assert!(parse(&["--leaderboard-url", ""], None).is_err());
+        assert!(parse(&["--leaderboard-url", " "], None).is_err());
+        assert!(parse(&[], Some("")).is_err());
+        // an empty variable doesn't matter once a flag overrides it
+        assert!(parse(&["--offline"], Some("")).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(
+            parse(&["--offline", "--leaderboard-url", "off"], None).is_err()
+        );
+        assert!(parse(&["--leaderboard-url"], None).is_err());
+        assert!(parse(&["--fast"], None).is_err());
+    }
+
+    #[test]
+    fn help_lists_the_leaderboard_flags() {
+        let help = Cli::command().render_help().to_string();
+        for flag in ["--leaderboard-url", "--leaderboard-file", "--offline"] {
+            assert!(help.contains(flag), "{} missing from {}", flag, help);
+        }
+    }
+}
@@ -1,52 +1,44 @@
 use bevy::prelude::*;
-use rand::prelude::random;
+
+pub use snake_core::food::generate_food_coords_with;
 
 #[derive(Component)]
 pub struct Food;
 
 pub fn generate_food_coords() -> crate::Position {
-    let x = (random::<f32>() * crate::WALL) as i32;
-    let y = (random::<f32>() * crate::WALL) as i32;
-
-    // reduce probability that food spawns on the wall
-    // 10% chance to regenerate if food is on the wall
-    if (x == 0
-        || x == crate::WALL as i32 - 1
-        || y == 0
-        || y == crate::WALL as i32 - 1)
-        && random::<f32>() > 0.9
-    {
-        return generate_food_coords();
-    }
-
-    crate::Position { x, y }
+    generate_food_coords_with(&mut rand::thread_rng())
 }
 
+/// Places food after the snake eats, drawing from the run's rng. The cell
+/// the snake grows into next is left out even before its segment exists,
+/// so food lands exactly where [`crate::sim::place_food`] would put it.
 pub fn food_spawner(
     mut commands: Commands,
     mut eat_reader: EventReader<crate::snake::EatEvent>,
     segments: Res<crate::snake::SnakeSegments>,
+    last_tail_position: Res<crate::snake::LastTailPosition>,
+    mut run: ResMut<crate::replay::CurrentRun>,
     mut positions: Query<&mut crate::Position, With<crate::snake::Segment>>,
 ) {
     // check if eat_reader event chain has value
     if eat_reader.read().next().is_some() {
         // grab positions of snake segments
-        let segment_positions = segments
+        let mut segment_positions = segments
             .iter()
-            .map(|e| match positions.get_mut(*e) {
-                Ok(p) => *p,
-                Err(_) => crate::Position {
-                    x: i32::MAX,
-                    y: i32::MAX,
-                },
-            })
+            .filter_map(|e| positions.get_mut(*e).ok().map(|p| *p))
             .collect::<Vec<crate::Position>>();
-
-        // generate food pos until it doesn't overlap with snake
-        let mut food_pos = generate_food_coords();
-        while segment_positions.contains(&food_pos) {
-            food_pos = generate_food_coords();
+        if let Some(tail) = last_tail_position.0 {
+            if !segment_positions.contains(&tail) {
+                segment_positions.push(tail);
+            }
         }
+
+        // the snake fills the board, there is nowhere left for food
+        let Some(food_pos) =
+            crate::sim::place_food(run.rng(), &segment_positions)
+        else {
+            return;
+        };
         // spawn food
         commands
             .spawn(SpriteBundle {
@@ -5,11 +5,11 @@ use crate::{
     Position,
 };
 
-#[derive(Resource, Default, Clone, Copy)]
-pub struct GameRules {
-    pub do_collide_walls: bool,
-    pub do_spawn_walls: bool,
-}
+pub use snake_core::game_mode::GameRules;
+
+/// Practice games show hints and are never submitted to the leaderboard
+#[derive(Resource, Default)]
+pub struct Practice(pub bool);
 
 #[derive(Event)]
 pub struct GameRuleChange(pub GameRules);
@@ -0,0 +1,109 @@
+//! The practice overlay: the shortest safe path to the food, and pockets of
+//! the board that would trap the snake. Never shown in ranked games.
+
+use bevy::prelude::*;
+
+use crate::game_mode::{GameRules, Practice, Wall};
+use crate::input::{Action, ActionState};
+use crate::snake::{SnakeHead, SnakeSegments};
+use crate::Position;
+
+#[derive(Component)]
+pub struct HintPiece;
+
+#[derive(Resource)]
+pub struct HintSettings {
+    pub show: bool,
+}
+
+impl Default for HintSettings {
+    fn default() -> Self {
+        Self { show: true }
+    }
+}
+
+/// Toggles the overlay while practicing
+pub fn toggle_hints(
+    actions: Res<ActionState>,
+    mut settings: ResMut<HintSettings>,
+) {
+    if actions.just_pressed(Action::ToggleHints) {
+        settings.show = !settings.show;
+    }
+}
+
+/// Redraws the overlay whenever the snake moves or new food appears
+pub fn update_hints(
+    mut commands: Commands,
+    timer: Res<crate::TickTimer>,
+    settings: Res<HintSettings>,
+    practice: Res<Practice>,
+    game_rules: Res<GameRules>,
+    segments: Res<SnakeSegments>,
+    heads: Query<&SnakeHead>,
+    positions: Query<&Position>,
+    food: Query<&Position, With<crate::food::Food>>,
+    new_food: Query<(), Added<crate::food::Food>>,
+    walls: Query<&Position, With<Wall>>,
+    pieces: Query<Entity, With<HintPiece>>,
+) {
+    let visible = practice.0 && settings.show;
+    let changed = timer.0.just_finished()
+        || !new_food.is_empty()
+        || settings.is_changed()
+        || practice.is_changed();
+    if !changed {
+        return;
+    }
+
+    for ent in pieces.iter() {
+        commands.entity(ent).despawn();
+    }
+    let Some(head) = heads.iter().next() else {
+        return;
+    };
+    if !visible {
+        return;
+    }
+
+    let snake = segments
+        .iter()
+        .filter_map(|e| positions.get(*e).ok().copied())
+        .collect::<Vec<_>>();
+    let walls = walls.iter().copied().collect::<Vec<_>>();
+    if snake.is_empty() || !crate::sim::in_bounds(snake[0]) {
+        return;
+    }
+
+    let path = food.iter().next().and_then(|food| {
+        crate::ai::safe_path(&snake, head.rot, *food, &walls, &game_rules)
+    });
+    let traps = crate::ai::trap_cells(&snake, &walls, &game_rules);
+
+    let path_color = Color::rgba(0.3, 0.8, 0.3, 0.25);
+    let trap_color = Color::rgba(0.9, 0.2, 0.2, 0.2);
+    let path = path.into_iter().flatten().map(|pos| (pos, path_color));
+    let traps = traps.into_iter().map(|pos| (pos, trap_color));
+    for (pos, color) in path.chain(traps) {
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                // draw underneath the snake and food
+                transform: Transform::from_xyz(0., 0., -1.),
+                ..default()
+            })
+            .insert(HintPiece)
+            .insert(pos)
+            .insert(crate::Size::square(crate::BLOCK_SIZE));
+    }
+}
+
+/// Removes the overlay when the game ends
+pub fn clear_hints(
+    mut commands: Commands,
+    pieces: Query<Entity, With<HintPiece>>,
+) {
+    for ent in pieces.iter() {
+        commands.entity(ent).despawn();
+    }
+}
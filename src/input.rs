@@ -0,0 +1,553 @@
+//! Maps raw input onto game actions, so the rest of the game never asks for
+//! a specific key or button. Key bindings can be changed from the menu and
+//! are persisted.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::{egui, EguiInput, EguiSet};
+
+use crate::snake::Direction;
+use crate::GameState;
+
+pub use snake_core::controls::ControlScheme;
+
+/// Storage key the bindings are saved under
+pub const BINDINGS_KEY: &str = "key_bindings";
+/// Storage key the chosen control scheme is saved under
+pub const SCHEME_KEY: &str = "control_scheme";
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum Action {
+    TurnUp,
+    TurnDown,
+    TurnLeft,
+    TurnRight,
+    Pause,
+    Restart,
+    QuickSpeed,
+    ToggleHints,
+    Confirm,
+    /// Relative turns, used by the relative and one-button schemes
+    TurnCounterClockwise,
+    TurnClockwise,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::TurnUp,
+        Action::TurnDown,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Pause,
+        Action::Restart,
+        Action::QuickSpeed,
+        Action::ToggleHints,
+        Action::Confirm,
+        Action::TurnCounterClockwise,
+        Action::TurnClockwise,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::TurnUp => "Turn Up",
+            Action::TurnDown => "Turn Down",
+            Action::TurnLeft => "Turn Left",
+            Action::TurnRight => "Turn Right",
+            Action::Pause => "Pause",
+            Action::Restart => "Restart",
+            Action::QuickSpeed => "Quick Speed",
+            Action::ToggleHints => "Toggle Hints",
+            Action::Confirm => "Confirm",
+            Action::TurnCounterClockwise => "Turn Counter-clockwise",
+            Action::TurnClockwise => "Turn Clockwise",
+        }
+    }
+
+    /// The direction a turn action steers in
+    pub fn direction(&self) -> Option<Direction> {
+        match self {
+            Action::TurnUp => Some(Direction::Up),
+            Action::TurnDown => Some(Direction::Down),
+            Action::TurnLeft => Some(Direction::Left),
+            Action::TurnRight => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+#[derive(
+    Resource, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize,
+)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (Action::TurnUp, vec![KeyCode::ArrowUp, KeyCode::KeyW]),
+                (Action::TurnDown, vec![KeyCode::ArrowDown, KeyCode::KeyS]),
+                (Action::TurnLeft, vec![KeyCode::ArrowLeft, KeyCode::KeyA]),
+                (Action::TurnRight, vec![KeyCode::ArrowRight, KeyCode::KeyD]),
+                (Action::Pause, vec![KeyCode::Escape]),
+                (Action::Restart, vec![KeyCode::Backslash]),
+                (Action::QuickSpeed, vec![KeyCode::KeyP]),
+                (Action::ToggleHints, vec![KeyCode::KeyH]),
+                (Action::Confirm, vec![KeyCode::Enter]),
+                (Action::TurnCounterClockwise, vec![KeyCode::KeyZ]),
+                (Action::TurnClockwise, vec![KeyCode::KeyX, KeyCode::Space]),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The saved bindings, or the defaults if there are none
+    pub fn load() -> Self {
+        let mut bindings =
+            crate::storage::load_json::<KeyBindings>(BINDINGS_KEY)
+                .unwrap_or_default();
+        // actions added since the bindings were saved get their defaults
+        for (action, keys) in KeyBindings::default().bindings {
+            if !bindings.bindings.iter().any(|(a, _)| *a == action) {
+                bindings.bindings.push((action, keys));
+            }
+        }
+        bindings
+    }
+
+    pub fn save(&self) {
+        crate::storage::save_json(BINDINGS_KEY, self);
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The action `key` is already bound to, if any
+    pub fn action_for(&self, key: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Binds `key` to `action`, failing with the conflicting action if the
+    /// key is already used elsewhere
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> Result<(), Action> {
+        match self.action_for(key) {
+            Some(existing) if existing == action => Ok(()),
+            Some(existing) => Err(existing),
+            None => {
+                match self.bindings.iter_mut().find(|(a, _)| *a == action) {
+                    Some((_, keys)) => keys.push(key),
+                    None => self.bindings.push((action, vec![key])),
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes `key` from `action`. Every action keeps at least one key, so
+    /// this returns false and leaves the last one in place.
+    pub fn unbind(&mut self, action: Action, key: KeyCode) -> bool {
+        match self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, keys)) if keys.len() > 1 => {
+                keys.retain(|k| *k != key);
+                true
+            }
+            Some((_, keys)) => !keys.contains(&key),
+            None => true,
+        }
+    }
+}
+
+/// Gamepad buttons for each action. The left stick steers as well.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct GamepadBindings {
+    bindings: Vec<(Action, Vec<GamepadButtonType>)>,
+    /// How far the stick has to be pushed before it counts as a turn
+    pub deadzone: f32,
+}
+
+impl Default for GamepadBindings {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (Action::TurnUp, vec![GamepadButtonType::DPadUp]),
+                (Action::TurnDown, vec![GamepadButtonType::DPadDown]),
+                (Action::TurnLeft, vec![GamepadButtonType::DPadLeft]),
+                (Action::TurnRight, vec![GamepadButtonType::DPadRight]),
+                (Action::Pause, vec![GamepadButtonType::Start]),
+                (Action::Restart, vec![GamepadButtonType::Select]),
+                (Action::ToggleHints, vec![GamepadButtonType::North]),
+                (Action::Confirm, vec![GamepadButtonType::South]),
+                (
+                    Action::TurnCounterClockwise,
+                    vec![GamepadButtonType::LeftTrigger],
+                ),
+                (
+                    Action::TurnClockwise,
+                    vec![GamepadButtonType::RightTrigger],
+                ),
+            ],
+            deadzone: 0.5,
+        }
+    }
+}
+
+impl GamepadBindings {
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, buttons)| buttons.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// The parts of [`ControlScheme`] that need the game: saving the chosen
+/// scheme and reading turns from the [`ActionState`]
+pub trait ControlSchemeExt {
+    fn load() -> Self;
+
+    fn save(&self);
+
+    /// The direction `action` turns to, if it steers in this scheme.
+    /// Relative turns are taken from `heading`, the direction the snake
+    /// will be moving in once earlier turns have been made.
+    fn turn(&self, action: Action, heading: Direction) -> Option<Direction>;
+}
+
+impl ControlSchemeExt for ControlScheme {
+    fn load() -> Self {
+        crate::storage::load_json(SCHEME_KEY).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        crate::storage::save_json(SCHEME_KEY, self);
+    }
+
+    fn turn(&self, action: Action, heading: Direction) -> Option<Direction> {
+        match (self, action) {
+            (ControlScheme::Absolute, action) => action.direction(),
+            (ControlScheme::Relative, Action::TurnCounterClockwise) => {
+                Some(heading.counter_clockwise())
+            }
+            (
+                ControlScheme::Relative | ControlScheme::OneButton,
+                Action::TurnClockwise,
+            ) => Some(heading.clockwise()),
+            _ => None,
+        }
+    }
+}
+
+/// Which actions are held and which started this frame, from any device
+#[derive(Resource, Debug, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    any_pressed: bool,
+    any_just_pressed: bool,
+}
+
+impl ActionState {
+    /// Is any key or button held, bound to an action or not?
+    pub fn any_pressed(&self) -> bool {
+        self.any_pressed
+    }
+
+    /// Did any key or button go down this frame, bound to an action or not?
+    pub fn any_just_pressed(&self) -> bool {
+        self.any_just_pressed
+    }
+
+    /// Records input from a device that has no actions of its own
+    pub fn mark_any_pressed(&mut self) {
+        self.any_pressed = true;
+    }
+
+    pub fn mark_any_just_pressed(&mut self) {
+        self.any_pressed = true;
+        self.any_just_pressed = true;
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Turn actions that started this frame
+    pub fn just_pressed_directions(
+        &self,
+    ) -> impl Iterator<Item = Direction> + '_ {
+        Action::ALL
+            .into_iter()
+            .filter(|action| self.just_pressed(*action))
+            .filter_map(|action| action.direction())
+    }
+
+    pub fn press(&mut self, action: Action) {
+        if self.pressed.insert(action) {
+            self.just_pressed.insert(action);
+        }
+    }
+
+    pub fn hold(&mut self, action: Action) {
+        self.pressed.insert(action);
+    }
+
+    pub fn clear(&mut self) {
+        self.pressed.clear();
+        self.just_pressed.clear();
+        self.any_pressed = false;
+        self.any_just_pressed = false;
+    }
+}
+
+/// Rebuilds the [`ActionState`] from the keyboard each frame
+pub fn keyboard_actions(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut actions: ResMut<ActionState>,
+) {
+    actions.clear();
+    actions.any_pressed = keyboard_input.get_pressed().next().is_some();
+    actions.any_just_pressed =
+        keyboard_input.get_just_pressed().next().is_some();
+    for action in Action::ALL {
+        let keys = bindings.keys(action);
+        if keyboard_input.any_just_pressed(keys.iter().copied()) {
+            actions.press(action);
+        } else if keyboard_input.any_pressed(keys.iter().copied()) {
+            actions.hold(action);
+        }
+    }
+}
+
+/// The direction each gamepad's left stick pointed last frame
+#[derive(Resource, Debug, Default)]
+pub struct StickState(HashMap<Gamepad, Option<Direction>>);
+
+/// The direction a stick points in, if pushed past the deadzone
+pub fn stick_direction(x: f32, y: f32, deadzone: f32) -> Option<Direction> {
+    if x.abs().max(y.abs()) < deadzone {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x > 0. {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if y > 0. {
+            Direction::Up
+        } else {
+            Direction::Down
+        })
+    }
+}
+
+fn turn_action(dir: Direction) -> Action {
+    match dir {
+        Direction::Up => Action::TurnUp,
+        Direction::Down => Action::TurnDown,
+        Direction::Left => Action::TurnLeft,
+        Direction::Right => Action::TurnRight,
+    }
+}
+
+/// Adds every connected gamepad's buttons and left stick to the
+/// [`ActionState`]. The stick only turns when it crosses into a new
+/// direction, so holding it doesn't repeat.
+pub fn gamepad_actions(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<GamepadBindings>,
+    mut sticks: ResMut<StickState>,
+    mut actions: ResMut<ActionState>,
+) {
+    actions.any_pressed |= buttons.get_pressed().next().is_some();
+    actions.any_just_pressed |= buttons.get_just_pressed().next().is_some();
+
+    for gamepad in gamepads.iter() {
+        for action in Action::ALL {
+            let buttons_for = bindings
+                .buttons(action)
+                .iter()
+                .map(|button| GamepadButton::new(gamepad, *button));
+            if buttons.any_just_pressed(buttons_for.clone()) {
+                actions.press(action);
+            } else if buttons.any_pressed(buttons_for) {
+                actions.hold(action);
+            }
+        }
+
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.)
+        };
+        let stick = stick_direction(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+            bindings.deadzone,
+        );
+        let previous = sticks.0.insert(gamepad, stick).flatten();
+        if let Some(dir) = stick {
+            if previous != Some(dir) {
+                actions.press(turn_action(dir));
+                actions.any_just_pressed = true;
+            } else {
+                actions.hold(turn_action(dir));
+            }
+        }
+    }
+}
+
+/// Lets the d-pad move focus between menu buttons and the confirm button
+/// press the focused one, by feeding egui the matching key presses. Only
+/// runs on the main menu and the name screen, so steering doesn't press
+/// buttons.
+pub fn gamepad_menu_navigation(
+    buttons: Res<ButtonInput<GamepadButton>>,
+    bindings: Res<GamepadBindings>,
+    mut egui_input: Query<&mut EguiInput>,
+) {
+    let pressed = |action: Action, button: Option<GamepadButtonType>| {
+        buttons.get_just_pressed().any(|b| {
+            bindings.buttons(action).contains(&b.button_type)
+                || Some(b.button_type) == button
+        })
+    };
+    let mut keys = Vec::new();
+    if pressed(Action::TurnDown, None) {
+        keys.push((egui::Key::Tab, egui::Modifiers::NONE));
+    }
+    if pressed(Action::TurnUp, None) {
+        keys.push((egui::Key::Tab, egui::Modifiers::SHIFT));
+    }
+    if pressed(Action::Confirm, None) {
+        keys.push((egui::Key::Enter, egui::Modifiers::NONE));
+    }
+    if pressed(Action::Pause, Some(GamepadButtonType::East)) {
+        keys.push((egui::Key::Escape, egui::Modifiers::NONE));
+    }
+
+    for mut input in egui_input.iter_mut() {
+        for (key, modifiers) in keys.iter() {
+            input.0.events.push(egui::Event::Key {
+                key: *key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: *modifiers,
+            });
+        }
+    }
+}
+
+/// Translates keyboard, gamepad and touch input into [`ActionState`] before
+/// the game's systems run
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyBindings>()
+            .init_resource::<GamepadBindings>()
+            .init_resource::<StickState>()
+            .init_resource::<ActionState>()
+            .init_resource::<Paused>()
+            .init_resource::<ControlScheme>()
+            .init_resource::<crate::touch::TouchSettings>()
+            .init_resource::<crate::touch::SwipeTracker>()
+            .init_resource::<crate::touch::TouchInUse>()
+            .add_systems(
+                PreUpdate,
+                (
+                    (
+                        keyboard_actions,
+                        gamepad_actions,
+                        crate::touch::touch_actions,
+                    )
+                        .chain()
+                        .after(InputSystem),
+                    gamepad_menu_navigation
+                        .after(InputSystem)
+                        .after(EguiSet::ProcessInput)
+                        .before(EguiSet::BeginFrame)
+                        .run_if(
+                            in_state(GameState::MainMenu)
+                                .or_else(in_state(GameState::EnterName)),
+                        ),
+                ),
+            );
+    }
+}
+
+/// Set while the game is paused
+#[derive(Resource, Default)]
+pub struct Paused(pub bool);
+
+pub fn toggle_pause(actions: Res<ActionState>, mut paused: ResMut<Paused>) {
+    if actions.just_pressed(Action::Pause) {
+        paused.0 = !paused.0;
+    }
+}
+
+pub fn not_paused(paused: Res<Paused>) -> bool {
+    !paused.0
+}
+
+pub fn unpause(mut paused: ResMut<Paused>) {
+    paused.0 = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_bind_to_one_action() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(bindings.bind(Action::Pause, KeyCode::KeyQ), Ok(()));
+        assert_eq!(bindings.bind(Action::Pause, KeyCode::KeyQ), Ok(()));
+        assert_eq!(
+            bindings.keys(Action::Pause),
+            [KeyCode::Escape, KeyCode::KeyQ]
+        );
+        assert_eq!(
+            bindings.bind(Action::Restart, KeyCode::KeyQ),
+            Err(Action::Pause)
+        );
+    }
+
+    #[test]
+    fn the_last_key_stays_bound() {
+        let mut bindings = KeyBindings::default();
+        assert!(bindings.unbind(Action::TurnUp, KeyCode::KeyW));
+        assert_eq!(bindings.keys(Action::TurnUp), [KeyCode::ArrowUp]);
+        assert!(!bindings.unbind(Action::TurnUp, KeyCode::ArrowUp));
+        assert_eq!(bindings.keys(Action::TurnUp), [KeyCode::ArrowUp]);
+        // removing a key the action doesn't have changes nothing
+        assert!(bindings.unbind(Action::TurnUp, KeyCode::KeyQ));
+    }
+}
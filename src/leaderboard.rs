@@ -0,0 +1,819 @@
+//! Where the leaderboard lives and how the game talks to it. The board is a
+//! [`LeaderboardBackend`], chosen at startup from runtime configuration so
+//! anyone can point the game at their own board, keep it in a file, or turn
+//! networking off entirely.
+//!
+//! Native builds read `--leaderboard-url <url>`, `--leaderboard-file
+//! <path>` or `--offline` from the command line, see `crate::cli`, then
+//! the `RUST_SNAKE_LEADERBOARD` environment variable. On the web the page's
+//! `?leaderboard=<url>` or `?offline` query parameters win over a
+//! `<meta name="rust-snake-leaderboard" content="<url>">` tag in the host
+//! page. Wherever a URL is accepted, `off` disables networking and `memory`
+//! keeps a throwaway board in memory.
+//!
+//! Runs are also kept in a [`LocalLeaderboard`] on the device, so there is
+//! always a board to show and nothing is lost while offline.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use futures::future::LocalBoxFuture;
+
+use crate::net::NetworkError;
+use crate::score::{
+    insert_best, BoardPage, BoardQuery, Highscore, Highscores, Standing,
+    NEIGHBOURS,
+};
+
+/// The leaderboard the game ships with
+pub const DEFAULT_URL: &str = "https://berintmoffett.com";
+
+/// Environment variable holding the leaderboard URL on native
+pub const URL_ENV: &str = "RUST_SNAKE_LEADERBOARD";
+
+/// Scores fetched from the top of the board
+pub const FETCH_LIMIT: usize = 50;
+
+/// A place scores are kept. Requests return futures that the game runs off
+/// the main thread, they aren't `Send` since browser requests can't be.
+pub trait LeaderboardBackend: Send + Sync {
+    /// A short name for the board to show in the UI
+    fn name(&self) -> String;
+
+    /// The best `limit` scores of a mode's board, best first
+    fn fetch_top(
+        &self,
+        mode: &str,
+        limit: usize,
+    ) -> LocalBoxFuture<'static, Result<Vec<Highscore>, NetworkError>>;
+
+    /// Adds a score to the board of the mode it was played in
+    fn submit(
+        &self,
+        highscore: Highscore,
+    ) -> LocalBoxFuture<'static, Result<(), NetworkError>>;
+
+    /// Where `score` would place on a mode's whole board, with up to
+    /// `around` entries either side
+    fn rank_of(
+        &self,
+        mode: &str,
+        score: u32,
+        around: usize,
+    ) -> LocalBoxFuture<'static, Result<Standing, NetworkError>>;
+
+    /// One page of a board, filtered by `query`
+    fn browse(
+        &self,
+        query: &BoardQuery,
+    ) -> LocalBoxFuture<'static, Result<BoardPage, NetworkError>>;
+}
+
+fn sorted(mut scores: Vec<Highscore>) -> Vec<Highscore> {
+    scores.sort_by_key(|s| std::cmp::Reverse(s.score));
+    scores
+}
+
+/// The scores played in `mode`, best first
+fn in_mode(scores: Vec<Highscore>, mode: &str) -> Vec<Highscore> {
+    sorted(scores.into_iter().filter(|s| s.mode() == mode).collect())
+}
+
+/// A leaderboard server speaking the `/api/snake-highscores` contract
+#[derive(Debug, Clone)]
+pub struct HttpBackend {
+    base_url: String,
+    /// Signs submissions that come without a fresh signature when set, see
+    /// [`crate::signing`]
+    key: Option<Vec<u8>>,
+}
+
+impl HttpBackend {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            key: None,
+        }
+    }
+
+    pub fn with_key(mut self, key: Option<Vec<u8>>) -> Self {
+        self.key = key;
+        self
+    }
+
+    fn scores_url(&self, mode: &str) -> String {
+        format!("{}/api/snake-highscores?mode={}", self.base_url, mode)
+    }
+}
+
+impl LeaderboardBackend for HttpBackend {
+    fn name(&self) -> String {
+        self.base_url
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .to_string()
+    }
+
+    fn fetch_top(
+        &self,
+        mode: &str,
+        limit: usize,
+    ) -> LocalBoxFuture<'static, Result<Vec<Highscore>, NetworkError>> {
+        let url = self.scores_url(mode);
+        let mode = mode.to_string();
+        Box::pin(async move {
+            let board = crate::net::get_json::<Highscores>(&url).await?;
+            // boards that predate modes send every score
+            let mut scores = in_mode(board.highscores, &mode);
+            scores.truncate(limit);
+            Ok(scores)
+        })
+    }
+
+    fn submit(
+        &self,
+        mut highscore: Highscore,
+    ) -> LocalBoxFuture<'static, Result<(), NetworkError>> {
+        let mode = highscore.mode();
+        let url = self.scores_url(mode);
+        // runs keep the signature they were entered with, so the board
+        // knows a retry for a repeat. One it would find stale has had its
+        // nonce forgotten by now and is signed again.
+        let signature = highscore.signature.as_ref();
+        let fresh = signature.is_some_and(crate::signing::is_fresh);
+        if let Some(key) = self.key.as_ref().filter(|_| !fresh) {
+            let signature = crate::signing::sign(key, &highscore, mode);
+            highscore.signature = Some(signature);
+        }
+        Box::pin(async move { crate::net::post_json(&url, &highscore).await })
+    }
+
+    fn rank_of(
+        &self,
+        mode: &str,
+        score: u32,
+        around: usize,
+    ) -> LocalBoxFuture<'static, Result<Standing, NetworkError>> {
+        let url = format!(
+            "{}/api/snake-highscores/rank?mode={}&score={}&around={}",
+            self.base_url, mode, score, around
+        );
+        let board_url = self.scores_url(mode);
+        let mode = mode.to_string();
+        Box::pin(async move {
+            match crate::net::get_json(&url).await {
+                // boards without ranking send everything to work it out from
+                Err(NetworkError::Status(404)) => {
+                    let board =
+                        crate::net::get_json::<Highscores>(&board_url).await?;
+                    let scores = in_mode(board.highscores, &mode);
+                    Ok(Standing::on(&scores, score, around))
+                }
+                res => res,
+            }
+        })
+    }
+
+    fn browse(
+        &self,
+        query: &BoardQuery,
+    ) -> LocalBoxFuture<'static, Result<BoardPage, NetworkError>> {
+        let mut params = vec![
+            ("mode", query.mode.clone()),
+            ("offset", query.offset.to_string()),
+            ("limit", query.limit.to_string()),
+            ("name", query.name.clone()),
+        ];
+        if let Some(within) = query.within {
+            params.push(("within", within.to_string()));
+        }
+        let url = reqwest::Url::parse_with_params(
+            &format!("{}/api/snake-highscores/page", self.base_url),
+            &params,
+        );
+        let board_url = self.scores_url(&query.mode);
+        let query = query.clone();
+        Box::pin(async move {
+            let url = url.map_err(|e| NetworkError::Request(e.to_string()))?;
+            match crate::net::get_json(url.as_str()).await {
+                // boards without paging send everything to page through
+                Err(NetworkError::Status(404)) => {
+                    let board =
+                        crate::net::get_json::<Highscores>(&board_url).await?;
+                    Ok(query.apply(&board.highscores, crate::signing::now()))
+                }
+                res => res,
+            }
+        })
+    }
+}
+
+/// A board kept in a JSON file, in the same format the server sends
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileBackend {
+    path: std::path::PathBuf,
+    /// Keeps read-modify-write cycles from interleaving
+    lock: Arc<Mutex<()>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileBackend {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn read(path: &std::path::Path) -> Result<Vec<Highscore>, NetworkError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str::<Highscores>(&text)
+                .map(|board| sorted(board.highscores))
+                .map_err(|e| NetworkError::Parse(e.to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Vec::new())
+            }
+            Err(e) => Err(NetworkError::Request(e.to_string())),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LeaderboardBackend for FileBackend {
+    fn name(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+
+    fn fetch_top(
+        &self,
+        mode: &str,
+        limit: usize,
+    ) -> LocalBoxFuture<'static, Result<Vec<Highscore>, NetworkError>> {
+        let board = self.clone();
+        let mode = mode.to_string();
+        Box::pin(async move {
+            let _guard = board.lock.lock().unwrap();
+            let mut scores = in_mode(Self::read(&board.path)?, &mode);
+            scores.truncate(limit);
+            Ok(scores)
+        })
+    }
+
+    fn submit(
+        &self,
+        highscore: Highscore,
+    ) -> LocalBoxFuture<'static, Result<(), NetworkError>> {
+        let board = self.clone();
+        Box::pin(async move {
+            let _guard = board.lock.lock().unwrap();
+            let mut scores = Self::read(&board.path)?;
+            insert_best(&mut scores, highscore);
+            let json = serde_json::to_string_pretty(&Highscores {
+                highscores: scores,
+            })
+            .map_err(|e| NetworkError::Parse(e.to_string()))?;
+            if let Some(dir) = board.path.parent() {
+                std::fs::create_dir_all(dir)
+                    .map_err(|e| NetworkError::Request(e.to_string()))?;
+            }
+            std::fs::write(&board.path, json)
+                .map_err(|e| NetworkError::Request(e.to_string()))
+        })
+    }
+
+    fn rank_of(
+        &self,
+        mode: &str,
+        score: u32,
+        around: usize,
+    ) -> LocalBoxFuture<'static, Result<Standing, NetworkError>> {
+        let board = self.clone();
+        let mode = mode.to_string();
+        Box::pin(async move {
+            let _guard = board.lock.lock().unwrap();
+            let scores = in_mode(Self::read(&board.path)?, &mode);
+            Ok(Standing::on(&scores, score, around))
+        })
+    }
+
+    fn browse(
+        &self,
+        query: &BoardQuery,
+    ) -> LocalBoxFuture<'static, Result<BoardPage, NetworkError>> {
+        let board = self.clone();
+        let query = query.clone();
+        Box::pin(async move {
+            let _guard = board.lock.lock().unwrap();
+            let scores = Self::read(&board.path)?;
+            Ok(query.apply(&scores, crate::signing::now()))
+        })
+    }
+}
+
+/// A board that only lives as long as the game, for trying things out and
+/// for driving the game in tests. Clones share the same scores.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend {
+    scores: Arc<Mutex<Vec<Highscore>>>,
+}
+
+impl MemoryBackend {
+    pub fn new(scores: Vec<Highscore>) -> Self {
+        Self {
+            scores: Arc::new(Mutex::new(sorted(scores))),
+        }
+    }
+
+    /// Every score on the board, best first
+    pub fn scores(&self) -> Vec<Highscore> {
+        self.scores.lock().unwrap().clone()
+    }
+}
+
+impl LeaderboardBackend for MemoryBackend {
+    fn name(&self) -> String {
+        "in memory".to_string()
+    }
+
+    fn fetch_top(
+        &self,
+        mode: &str,
+        limit: usize,
+    ) -> LocalBoxFuture<'static, Result<Vec<Highscore>, NetworkError>> {
+        let mut scores = in_mode(self.scores(), mode);
+        scores.truncate(limit);
+        Box::pin(async move { Ok(scores) })
+    }
+
+    fn submit(
+        &self,
+        highscore: Highscore,
+    ) -> LocalBoxFuture<'static, Result<(), NetworkError>> {
+        insert_best(&mut self.scores.lock().unwrap(), highscore);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn rank_of(
+        &self,
+        mode: &str,
+        score: u32,
+        around: usize,
+    ) -> LocalBoxFuture<'static, Result<Standing, NetworkError>> {
+        let standing =
+            Standing::on(&in_mode(self.scores(), mode), score, around);
+        Box::pin(async move { Ok(standing) })
+    }
+
+    fn browse(
+        &self,
+        query: &BoardQuery,
+    ) -> LocalBoxFuture<'static, Result<BoardPage, NetworkError>> {
+        let page = query.apply(&self.scores(), crate::signing::now());
+        Box::pin(async move { Ok(page) })
+    }
+}
+
+/// Which backend to start with
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub enum LeaderboardConfig {
+    /// A leaderboard server at this base URL
+    Http(String),
+    /// A JSON file on this machine
+    #[cfg(not(target_arch = "wasm32"))]
+    File(std::path::PathBuf),
+    Memory,
+    /// No leaderboard beyond the one on the device
+    Offline,
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self::online(DEFAULT_URL)
+    }
+}
+
+impl LeaderboardConfig {
+    pub fn online(url: &str) -> Self {
+        Self::Http(url.trim_end_matches('/').to_string())
+    }
+
+    /// Reads a configured value, where `off` means no networking. An empty
+    /// value is refused rather than taken for either.
+    pub fn from_value(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "" => Err("no leaderboard URL given, use `off` to play offline"
+                .to_string()),
+            "off" => Ok(Self::Offline),
+            "memory" => Ok(Self::Memory),
+            url => Ok(Self::online(url)),
+        }
+    }
+
+    /// The backend to talk to, `None` when offline
+    pub fn backend(&self) -> Option<Arc<dyn LeaderboardBackend>> {
+        match self {
+            Self::Http(url) => Some(Arc::new(
+                HttpBackend::new(url)
+                    .with_key(crate::signing::configured_key()),
+            )),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::File(path) => Some(Arc::new(FileBackend::new(path))),
+            Self::Memory => Some(Arc::new(MemoryBackend::default())),
+            Self::Offline => None,
+        }
+    }
+
+    /// Reads the page's query string, then the host page's meta tag
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_page() -> Self {
+        let Some(window) = web_sys::window() else {
+            return Self::default();
+        };
+
+        // a broken setting is skipped, there's nowhere to report it yet
+        let read = |url: String| match Self::from_value(&url) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                warn!("Ignoring the leaderboard setting: {}", e);
+                None
+            }
+        };
+
+        let params = window.location().search().ok().and_then(|search| {
+            web_sys::UrlSearchParams::new_with_str(&search).ok()
+        });
+        if let Some(params) = params {
+            if params.has("offline") {
+                return Self::Offline;
+            }
+            if let Some(config) = params.get("leaderboard").and_then(read) {
+                return config;
+            }
+        }
+
+        window
+            .document()
+            .and_then(|document| {
+                document
+                    .query_selector("meta[name=\"rust-snake-leaderboard\"]")
+                    .ok()
+                    .flatten()
+            })
+            .and_then(|meta| meta.get_attribute("content"))
+            .and_then(read)
+            .unwrap_or_default()
+    }
+}
+
+/// A finished request to the backend
+#[derive(Debug)]
+pub enum BackendReply {
+    /// The top of one mode's board
+    Fetched(String, Result<Vec<Highscore>, NetworkError>),
+    /// Where a score places on one mode's board
+    Standing(String, Result<Standing, NetworkError>),
+    /// A page for the leaderboard browser
+    Browsed(BoardQuery, Result<BoardPage, NetworkError>),
+    Submitted(Highscore, Result<(), NetworkError>),
+}
+
+/// The backend chosen at startup and the latest scores fetched from it
+#[derive(Resource)]
+pub struct Leaderboard {
+    backend: Option<Arc<dyn LeaderboardBackend>>,
+    /// Keyed by mode
+    top: HashMap<String, Vec<Highscore>>,
+    /// Where the last run placed and on which mode's board
+    standing: Option<(String, Standing)>,
+    /// The browser's last page and the query it answers
+    page: Option<(BoardQuery, Result<BoardPage, NetworkError>)>,
+    /// The page on its way, so it isn't asked for every frame
+    browsing: Option<BoardQuery>,
+    /// The run's place is on its way. The run waits for it before being
+    /// submitted, or the board could count the run against itself.
+    ranking: bool,
+    replies_tx: Sender<BackendReply>,
+    replies: Mutex<Receiver<BackendReply>>,
+}
+
+impl Leaderboard {
+    pub fn new(backend: Option<Arc<dyn LeaderboardBackend>>) -> Self {
+        let (replies_tx, replies) = mpsc::channel();
+        Self {
+            backend,
+            top: HashMap::new(),
+            standing: None,
+            page: None,
+            browsing: None,
+            ranking: false,
+            replies_tx,
+            replies: Mutex::new(replies),
+        }
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// A short name for the board to show in the UI
+    pub fn label(&self) -> String {
+        match &self.backend {
+            Some(backend) => backend.name(),
+            None => "offline".to_string(),
+        }
+    }
+
+    /// The scores last fetched for `mode`, best first
+    pub fn top(&self, mode: &str) -> &[Highscore] {
+        self.top.get(mode).map_or(&[], |top| top)
+    }
+
+    pub fn set_top(&mut self, mode: &str, top: Vec<Highscore>) {
+        self.top.insert(mode.to_string(), top);
+    }
+
+    /// Where the last run placed, if it was played in `mode`
+    pub fn standing(&self, mode: &str) -> Option<&Standing> {
+        self.standing
+            .as_ref()
+            .filter(|(played, _)| played == mode)
+            .map(|(_, standing)| standing)
+    }
+
+    pub fn set_standing(&mut self, mode: &str, standing: Standing) {
+        self.ranking = false;
+        self.standing = Some((mode.to_string(), standing));
+    }
+
+    /// Whether the last run's place is still being looked up
+    pub fn is_ranking(&self) -> bool {
+        self.ranking
+    }
+
+    /// Gives up on the run's place, keeping the guess made from the top
+    pub fn rank_failed(&mut self) {
+        self.ranking = false;
+    }
+
+    /// Asks in the background where `score` places on a mode's whole
+    /// board. Returns `false` when offline.
+    pub fn fetch_standing(&mut self, mode: &str, score: u32) -> bool {
+        let Some(backend) = self.backend.clone() else {
+            return false;
+        };
+        self.ranking = true;
+        let tx = self.replies_tx.clone();
+        let mode = mode.to_string();
+        crate::run_async(async move {
+            let asked = mode.clone();
+            let res = tokio::task::spawn_local(async move {
+                backend.rank_of(&asked, score, NEIGHBOURS).await
+            })
+            .await
+            .unwrap_or_else(|e| Err(NetworkError::Request(e.to_string())));
+            let _ = tx.send(BackendReply::Standing(mode, res));
+        });
+        true
+    }
+
+    /// The page answering `query`, if it has arrived
+    pub fn page(
+        &self,
+        query: &BoardQuery,
+    ) -> Option<&Result<BoardPage, NetworkError>> {
+        self.page
+            .as_ref()
+            .filter(|(answered, _)| answered == query)
+            .map(|(_, page)| page)
+    }
+
+    pub fn set_page(
+        &mut self,
+        query: BoardQuery,
+        page: Result<BoardPage, NetworkError>,
+    ) {
+        if self.browsing.as_ref() == Some(&query) {
+            self.browsing = None;
+        }
+        self.page = Some((query, page));
+    }
+
+    /// Drops the last page so the browser asks again
+    pub fn forget_page(&mut self) {
+        self.page = None;
+    }
+
+    /// Fetches a page for the browser in the background, unless it has
+    /// arrived or is on its way already. Returns `false` when offline.
+    pub fn browse(&mut self, query: &BoardQuery) -> bool {
+        let Some(backend) = self.backend.clone() else {
+            return false;
+        };
+        if self.page(query).is_some() || self.browsing.as_ref() == Some(query) {
+            return true;
+        }
+        self.browsing = Some(query.clone());
+        let tx = self.replies_tx.clone();
+        let query = query.clone();
+        crate::run_async(async move {
+            let asked = query.clone();
+            let res = tokio::task::spawn_local(async move {
+                backend.browse(&asked).await
+            })
+            .await
+            .unwrap_or_else(|e| Err(NetworkError::Request(e.to_string())));
+            let _ = tx.send(BackendReply::Browsed(query, res));
+        });
+        true
+    }
+
+    /// Fetches the top of a mode's board in the background. Returns
+    /// `false` when offline.
+    pub fn fetch(&self, mode: &str) -> bool {
+        let Some(backend) = self.backend.clone() else {
+            return false;
+        };
+        let tx = self.replies_tx.clone();
+        let mode = mode.to_string();
+        crate::run_async(async move {
+            let fetched = mode.clone();
+            let res = tokio::task::spawn_local(async move {
+                backend.fetch_top(&fetched, FETCH_LIMIT).await
+            })
+            .await
+            .unwrap_or_else(|e| Err(NetworkError::Request(e.to_string())));
+            let _ = tx.send(BackendReply::Fetched(mode, res));
+        });
+        true
+    }
+
+    /// Submits a score in the background. Returns `false` when offline.
+    pub fn submit(&self, highscore: Highscore) -> bool {
+        let Some(backend) = self.backend.clone() else {
+            return false;
+        };
+        let tx = self.replies_tx.clone();
+        crate::run_async(async move {
+            let submitted = highscore.clone();
+            let res = tokio::task::spawn_local(async move {
+                backend.submit(submitted).await
+            })
+            .await
+            .unwrap_or_else(|e| Err(NetworkError::Request(e.to_string())));
+            let _ = tx.send(BackendReply::Submitted(highscore, res));
+        });
+        true
+    }
+
+    /// Requests that have finished since the last call
+    pub fn replies(&self) -> Vec<BackendReply> {
+        self.replies.lock().unwrap().try_iter().collect()
+    }
+}
+
+/// Storage key the local leaderboard is saved under
+pub const LOCAL_KEY: &str = "local_scores";
+
+/// Scores kept on this device
+pub const LOCAL_LIMIT: usize = 50;
+
+/// Every run finished on this device, kept whether or not the online board
+/// could be reached. Runs that haven't reached the online board yet are
+/// sent again once it answers.
+#[derive(
+    Resource, Debug, Clone, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(default)]
+pub struct LocalLeaderboard {
+    /// Best first
+    scores: Vec<Highscore>,
+    /// Waiting to be submitted online, oldest first
+    pending: Vec<Highscore>,
+    /// Submissions on their way, so they aren't sent twice
+    #[serde(skip)]
+    sending: Vec<Highscore>,
+    /// Runs the online board refused and why, oldest first
+    refused: Vec<(Highscore, String)>,
+}
+
+impl LocalLeaderboard {
+    pub fn load() -> Self {
+        crate::storage::load_json(LOCAL_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        crate::storage::save_json(LOCAL_KEY, self);
+    }
+
+    pub fn scores(&self) -> &[Highscore] {
+        &self.scores
+    }
+
+    pub fn pending(&self) -> &[Highscore] {
+        &self.pending
+    }
+
+    pub fn refused(&self) -> &[(Highscore, String)] {
+        &self.refused
+    }
+
+    /// Adds a finished run and queues it for the online board
+    pub fn record(&mut self, highscore: Highscore) {
+        let place = self
+            .scores
+            .iter()
+            .position(|s| s.score < highscore.score)
+            .unwrap_or(self.scores.len());
+        self.scores.insert(place, highscore.clone());
+        self.scores.truncate(LOCAL_LIMIT);
+
+        self.pending.push(highscore);
+        if self.pending.len() > LOCAL_LIMIT {
+            self.pending.remove(0);
+        }
+        self.save();
+    }
+
+    /// Pending runs not already on their way
+    pub fn unsent(&self) -> Vec<Highscore> {
+        self.pending
+            .iter()
+            .filter(|s| !self.sending.contains(s))
+            .cloned()
+            .collect()
+    }
+
+    /// Notes that a submission is on its way, `false` if it already was
+    pub fn mark_sending(&mut self, highscore: &Highscore) -> bool {
+        if self.sending.contains(highscore) {
+            return false;
+        }
+        self.sending.push(highscore.clone());
+        true
+    }
+
+    /// Records how a submission went. Accepted and refused runs stop being
+    /// pending, runs that didn't get through stay for the next attempt.
+    pub fn submitted(
+        &mut self,
+        highscore: &Highscore,
+        res: &Result<(), NetworkError>,
+    ) {
+        if let Some(i) = self.sending.iter().position(|s| s == highscore) {
+            self.sending.remove(i);
+        }
+        match res {
+            Ok(()) => {}
+            Err(NetworkError::Rejected(reason)) => {
+                self.refused.push((highscore.clone(), reason.clone()));
+                if self.refused.len() > LOCAL_LIMIT {
+                    self.refused.remove(0);
+                }
+            }
+            Err(_) => return,
+        }
+        if let Some(i) = self.pending.iter().position(|s| s == highscore) {
+            self.pending.remove(i);
+        }
+        self.save();
+    }
+
+    /// A mode's online board with this device's runs in that mode merged
+    /// in, best first. Runs the online board already lists aren't
+    /// repeated, each player shows only their best, and the flag marks
+    /// runs only this device knows about.
+    pub fn merged(
+        &self,
+        mode: &str,
+        online: &[Highscore],
+    ) -> Vec<(Highscore, bool)> {
+        let mut merged = online
+            .iter()
+            .map(|s| (s.clone(), false))
+            .collect::<Vec<_>>();
+        for score in self.scores.iter().filter(|s| s.mode() == mode) {
+            let listed = online.iter().any(|s| {
+                (s.name == score.name && s.score == score.score)
+                    || (s.same_player(score) && s.score >= score.score)
+            });
+            if !listed {
+                merged.push((score.clone(), true));
+            }
+        }
+        merged.sort_by_key(|(s, _)| std::cmp::Reverse(s.score));
+
+        let mut players = Vec::new();
+        merged.retain(|(s, _)| match &s.player_id {
+            Some(id) if players.contains(id) => false,
+            Some(id) => {
+                players.push(id.clone());
+                true
+            }
+            None => true,
+        });
+        merged
+    }
+}
@@ -1,26 +1,41 @@
 #![allow(clippy::too_many_arguments)]
 
+pub mod attract;
+#[cfg(all(feature = "tools", not(target_arch = "wasm32")))]
+pub mod bot;
 pub mod cheats;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
 pub mod debug;
 pub mod food;
 pub mod game_mode;
+pub mod hints;
+pub mod input;
+pub mod leaderboard;
+pub mod moderation;
+pub mod net;
+pub mod profile;
+pub mod replay;
 pub mod score;
 pub mod snake;
+pub mod storage;
+pub mod touch;
 pub mod ui;
 
+pub use snake_core::{ai, signing, sim};
+
 use bevy::prelude::*;
 use cheats::ScoreBlocker;
 use futures::Future;
 use rand::random;
 use score::LeaderboardEarned;
-use score::HIGHSCORES;
 use snake::TickAccum;
 
-pub const TICK_RATE: f32 = 5.; // Number of updates per second
-pub const TICK_INCREASE: f32 = 0.20; // How much to increase tick rate by on eat
-pub const BIG_TICK_INCREASE: f32 = 0.50; // How much to increase tick rate by on eat every 10th score
+pub use snake_core::{
+    Position, BIG_TICK_INCREASE, TICK_INCREASE, TICK_RATE, WALL,
+};
+
 pub const BLOCK_SIZE: f32 = 0.8;
-pub const WALL: f32 = 20.;
 
 #[cfg(debug_assertions)]
 #[global_allocator]
@@ -44,12 +59,6 @@ pub enum WindowState {
     Scaled,
 }
 
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Position {
-    x: i32,
-    y: i32,
-}
-
 #[derive(Component)]
 pub struct Size {
     width: f32,
@@ -65,8 +74,20 @@ impl Size {
     }
 }
 
-#[derive(Event)]
-pub struct GameOverEvent;
+/// The snake died. Sent once per run, for the first thing it hit.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameOverEvent {
+    pub cause: crate::sim::DeathCause,
+    /// The cell the head moved onto, off the board for
+    /// [`DeathCause::OutOfBounds`](crate::sim::DeathCause::OutOfBounds)
+    pub cell: Position,
+    /// The tick of the run the snake died on, counting from 1
+    pub tick: u64,
+}
+
+/// Marks the cell the snake died on while the game over screen is up
+#[derive(Component)]
+pub struct FatalCell;
 
 #[derive(Event)]
 pub struct ViewLeaderboardEvent;
@@ -83,6 +104,10 @@ pub struct TickTimer(pub Timer);
 #[derive(Resource)]
 pub struct Name(pub String);
 
+/// A name the moderator refused and why, shown while it's still entered
+#[derive(Resource, Default)]
+pub struct NameFeedback(pub Option<(String, crate::moderation::NameProblem)>);
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn run_async<F>(future: F)
 where
@@ -153,7 +178,7 @@ pub fn position_translation(
         transform.translation = Vec3::new(
             convert(pos.x as f32, window.width(), WALL),
             convert(pos.y as f32, window.height(), WALL),
-            0.,
+            transform.translation.z,
         )
     }
 }
@@ -212,25 +237,59 @@ pub fn game_over(
     food: Query<Entity, With<crate::food::Food>>,
     segments: Query<Entity, With<crate::snake::Segment>>,
     walls: Query<Entity, With<crate::game_mode::Wall>>,
+    mut wall_queue: ResMut<crate::game_mode::WallQueue>,
     mut next_state: ResMut<NextState<GameState>>,
     state: Res<State<GameState>>,
     mut enter_name_event: EventWriter<CalcHighscoresEvent>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<crate::input::ActionState>,
+    practice: Res<crate::game_mode::Practice>,
+    external: Option<Res<crate::snake::ExternalControl>>,
 ) {
     // despawn all text, snake segments, and food
-    if reader.read().next().is_some() {
+    if let Some(death) = reader.read().next() {
         next_state.set(GameState::GameOver);
 
         for ent in food.iter().chain(segments.iter()).chain(walls.iter()) {
             commands.entity(ent).despawn();
         }
+        *wall_queue = default();
+
+        // a head that left the board is shown on the edge it left from
+        let cell = Position::new(
+            death.cell.x.clamp(0, crate::sim::BOARD_MAX),
+            death.cell.y.clamp(0, crate::sim::BOARD_MAX),
+        );
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::RED,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(FatalCell)
+            .insert(cell)
+            .insert(Size::square(BLOCK_SIZE));
     }
 
-    if state.get() == &GameState::GameOver
-        && keyboard_input.get_pressed().next().is_some()
-    {
-        next_state.set(GameState::EnterName);
-        enter_name_event.send(CalcHighscoresEvent);
+    if state.get() == &GameState::GameOver && actions.any_pressed() {
+        if practice.0 || external.is_some() {
+            // practice and bot runs are unranked, skip straight past name
+            // entry
+            next_state.set(GameState::ReadyToReset);
+        } else {
+            next_state.set(GameState::EnterName);
+            enter_name_event.send(CalcHighscoresEvent);
+        }
+    }
+}
+
+pub fn clear_fatal_cell(
+    mut commands: Commands,
+    cells: Query<Entity, With<FatalCell>>,
+) {
+    for ent in cells.iter() {
+        commands.entity(ent).despawn();
     }
 }
 
@@ -238,20 +297,28 @@ pub fn calc_highscores(
     score: Res<crate::score::Score>,
     mut leaderboard_place_earned: ResMut<crate::score::LeaderboardEarned>,
     mut calc_highscores_event: EventReader<CalcHighscoresEvent>,
+    local_scores: Res<crate::leaderboard::LocalLeaderboard>,
+    mut leaderboard: ResMut<crate::leaderboard::Leaderboard>,
+    run: Res<crate::replay::CurrentRun>,
 ) {
     if calc_highscores_event.read().next().is_some() {
-        let hs_arc = HIGHSCORES.get().unwrap();
-        let highscores = hs_arc.lock().unwrap();
-        let mut i = 0;
-        while i < 5 && score.0 > highscores.highscores[i].score {
-            i += 1;
-        }
-
-        if i > 0 {
-            *leaderboard_place_earned = LeaderboardEarned::Placed(i as u8);
-        } else {
-            *leaderboard_place_earned = LeaderboardEarned::NotPlaced;
-        }
+        // only runs in the same mode compete
+        let mode = run.replay.rules.name();
+        let highscores: Vec<_> = local_scores
+            .merged(mode, leaderboard.top(mode))
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect();
+
+        // placed against what is known now, until the board answers
+        let standing = crate::score::Standing::on(
+            &highscores,
+            score.0,
+            crate::score::NEIGHBOURS,
+        );
+        *leaderboard_place_earned = LeaderboardEarned::for_rank(standing.rank);
+        leaderboard.set_standing(mode, standing);
+        leaderboard.fetch_standing(mode, score.0);
     }
 }
 
@@ -259,31 +326,104 @@ pub fn enter_name(
     mut next_state: ResMut<NextState<GameState>>,
     mut ev_char: EventReader<ReceivedCharacter>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<crate::input::ActionState>,
     mut name: ResMut<Name>,
     mut leaderboard_event: EventWriter<ViewLeaderboardEvent>,
     mut acquire_highscores: EventWriter<crate::score::AcquireHighscores>,
     mut send_highscores: EventWriter<crate::score::SendHighscores>,
     score: Res<crate::score::Score>,
+    scheme: Res<crate::input::ControlScheme>,
+    run: Res<crate::replay::CurrentRun>,
+    leaderboard: Res<crate::leaderboard::Leaderboard>,
+    mut local_scores: ResMut<crate::leaderboard::LocalLeaderboard>,
+    mut submission: ResMut<crate::score::Submission>,
+    (moderator, mut feedback, mut held): (
+        Res<crate::moderation::Moderator>,
+        ResMut<NameFeedback>,
+        Local<bool>,
+    ),
+    mut profile: ResMut<crate::profile::PlayerProfile>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Enter) {
+    use crate::score::Submission;
+
+    // once the name is in, wait for the board's verdict before moving on
+    let confirmed = actions.just_pressed(crate::input::Action::Confirm);
+    let done = match &*submission {
+        Submission::Idle => false,
+        Submission::Sending(_) | Submission::Rejected(_) => confirmed,
+        Submission::Accepted | Submission::Failed(_) => true,
+    };
+    if done {
+        *submission = Submission::Idle;
+        next_state.set(GameState::ViewingLeaderboard);
+        leaderboard_event.send(ViewLeaderboardEvent);
+    }
+    if *submission != Submission::Idle || done {
+        return;
+    }
+
+    // a refused name has to be changed by hand
+    let skip = profile.skips_name_entry() && feedback.0.is_none();
+    if confirmed || skip || *held {
+        // the run's place is looked up before the run is on the board, or
+        // the board would count the run against itself
+        if leaderboard.is_ranking() {
+            *held = true;
+            return;
+        }
+        *held = false;
         name.0 = name.0.chars().filter(|c| c.is_alphanumeric()).collect();
-        if name.0.is_empty() {
+        let named = !name.0.is_empty();
+        if !named {
             name.0 = "Anonymous".to_string();
         }
-        let highscore = crate::score::Highscore {
+        // leave the name to be changed, the board would refuse it too
+        if let Err(problem) = moderator.check(&name.0) {
+            feedback.0 = Some((name.0.clone(), problem));
+            return;
+        }
+        feedback.0 = None;
+        if named && name.0 != profile.name {
+            profile.name = name.0.clone();
+            profile.save();
+        }
+        let mut highscore = crate::score::Highscore {
+            version: crate::score::HIGHSCORE_VERSION,
             name: name.0.clone(),
             score: score.0,
+            controls: *scheme,
+            rules: run.replay.rules,
+            board_size: crate::sim::BOARD_CELLS,
+            replay: Some(run.replay.clone()),
+            signature: None,
+            timestamp: Some(crate::signing::now()),
+            player_id: Some(profile.id.clone()),
+            game_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            stats: run.stats(),
         };
+        // signed once, so every retry and resend carries the same nonce
+        if let Some(key) = crate::signing::configured_key() {
+            let mode = highscore.mode();
+            let signature = crate::signing::sign(&key, &highscore, mode);
+            highscore.signature = Some(signature);
+        }
+
+        local_scores.record(highscore.clone());
 
         // send highscore to server
-        send_highscores.send(crate::score::SendHighscores(highscore));
+        send_highscores.send(crate::score::SendHighscores(highscore.clone()));
         acquire_highscores.send(crate::score::AcquireHighscores);
 
         name.0.clear();
 
-        // show leaderboard
-        next_state.set(GameState::ViewingLeaderboard);
-        leaderboard_event.send(ViewLeaderboardEvent);
+        if leaderboard.is_online() {
+            *submission = Submission::Sending(highscore);
+        } else {
+            // show leaderboard
+            next_state.set(GameState::ViewingLeaderboard);
+            leaderboard_event.send(ViewLeaderboardEvent);
+        }
+        return;
     }
 
     // wasm does not send ReceivedCharacter events for backspace
@@ -299,7 +439,8 @@ pub fn enter_name(
         }
         // if keyboard_input.just_pressed(KeyCode::Backspace) {
         // }
-        if name.0.len() <= 10 && ev.char.is_ascii() {
+        if name.0.len() < crate::moderation::MAX_NAME_LEN && ev.char.is_ascii()
+        {
             if ev.char == "\n" || ev.char == "\r" {
                 continue;
             }
@@ -308,11 +449,19 @@ pub fn enter_name(
     }
 }
 
+/// Starts the name screen with the name the player used last
+pub fn prefill_name(
+    mut name: ResMut<Name>,
+    profile: Res<crate::profile::PlayerProfile>,
+) {
+    name.0 = profile.name.clone();
+}
+
 pub fn leaderboard(
     mut next_state: ResMut<NextState<GameState>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<crate::input::ActionState>,
 ) {
-    if keyboard_input.get_pressed().next().is_some() {
+    if actions.any_pressed() {
         next_state.set(GameState::ReadyToReset);
     }
 }
@@ -321,14 +470,21 @@ pub fn leaderboard(
 pub struct ResetEvent;
 
 pub fn awaiting_reset(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<crate::input::ActionState>,
     mut reset_writer: EventWriter<ResetEvent>,
 ) {
-    if keyboard_input.get_just_pressed().next().is_some() {
+    if actions.any_just_pressed() {
         reset_writer.send(ResetEvent);
     }
 }
 
+/// Everything on the board, which a restart in the middle of a game clears
+type OnBoard = Or<(
+    With<crate::food::Food>,
+    With<crate::snake::Segment>,
+    With<crate::game_mode::Wall>,
+)>;
+
 /// Reset game when reset event is sent
 pub fn reset_game(
     mut commands: Commands,
@@ -340,20 +496,24 @@ pub fn reset_game(
     mut tick_timer: ResMut<crate::TickTimer>,
     mut score: ResMut<crate::score::Score>,
     mut score_blocker: ResMut<ScoreBlocker>,
+    mut input_queue: ResMut<crate::snake::InputQueue>,
     last_tail_position: ResMut<crate::snake::LastTailPosition>,
     mut reset_reader: EventReader<ResetEvent>,
-    food: Query<Entity, With<crate::food::Food>>,
-    segments: Query<Entity, With<crate::snake::Segment>>,
+    mut run: ResMut<crate::replay::CurrentRun>,
+    game_rules: Res<crate::game_mode::GameRules>,
+    mut wall_queue: ResMut<crate::game_mode::WallQueue>,
+    board: Query<Entity, OnBoard>,
 ) {
     if reset_reader.read().next().is_none() {
         return;
     }
 
-    for ent in food.iter().chain(segments.iter()) {
+    for ent in board.iter() {
         commands.entity(ent).despawn();
     }
-    let rand: u8 = random();
-    let dir: snake::Direction = rand.into();
+    *wall_queue = default();
+    *run = crate::replay::CurrentRun::new(random(), *game_rules);
+    let dir = run.heading();
 
     next_state.set(GameState::Playing);
     crate::snake::add_snake(commands, segments_res, last_tail_position, dir);
@@ -363,4 +523,5 @@ pub fn reset_game(
     tick_timer.0 = Timer::from_seconds(1. / TICK_RATE, TimerMode::Repeating);
     score.0 = 0;
     score_blocker.0 = 0;
+    input_queue.0.clear();
 }
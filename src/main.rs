@@ -3,11 +3,17 @@ use bevy::log::LogPlugin;
 use bevy::prelude::*;
 
 use bevy_egui::EguiPlugin;
+use rust_snake::attract::*;
 use rust_snake::cheats::*;
 use rust_snake::food::*;
 use rust_snake::game_mode::*;
+use rust_snake::hints::*;
+use rust_snake::input::*;
+use rust_snake::leaderboard::{Leaderboard, LocalLeaderboard};
+use rust_snake::replay::CurrentRun;
 use rust_snake::score::*;
 use rust_snake::snake::*;
+use rust_snake::touch::*;
 use rust_snake::ui::*;
 use rust_snake::*;
 
@@ -15,11 +21,44 @@ use rust_snake::*;
 use rust_snake::debug::*;
 
 fn main() {
-    #[cfg(target_arch = "wasm32-unknown-unknown")]
+    #[cfg(target_arch = "wasm32")]
     {
         wasm_logger::init(wasm_logger::Config::default());
         std::panic::set_hook(Box::new(console_error_panic_hook::hook));
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let args = match rust_snake::cli::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => e.exit(),
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let leaderboard_config = args.leaderboard;
+    #[cfg(target_arch = "wasm32")]
+    let leaderboard_config =
+        rust_snake::leaderboard::LeaderboardConfig::from_page();
+    #[cfg(all(feature = "tools", not(target_arch = "wasm32")))]
+    let bot_args = args.bot;
+
+    #[cfg(all(feature = "tools", not(target_arch = "wasm32")))]
+    if let Some(args) = bot_args.as_ref().filter(|args| args.headless) {
+        match rust_snake::bot::run_headless(
+            &args.command,
+            args.rules,
+            args.seed,
+            args.budget,
+        ) {
+            Ok(report) => {
+                println!("{}", serde_json::to_string(&report).unwrap());
+                return;
+            }
+            Err(e) => {
+                eprintln!("Failed to start bot: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     let mut app = App::new();
 
     // Insert resources
@@ -34,17 +73,28 @@ fn main() {
         .insert_resource(Score::default())
         .insert_resource(LeaderboardEarned::NotPlaced)
         .insert_resource(LastPressed::default())
-        .insert_resource(InputQueue::default())
-        .insert_resource(InputQueueTimer(Timer::from_seconds(
-            1. / 2. * TICK_RATE,
-            TimerMode::Once,
-        )))
+        .insert_resource(InputQueue::load())
         .insert_resource(TickAccum(TICK_RATE))
         .insert_resource(ScoreBlocker(0))
         .insert_resource(rust_snake::Name("".to_string()))
+        .insert_resource(rust_snake::NameFeedback::default())
+        .insert_resource(rust_snake::profile::PlayerProfile::load())
+        .insert_resource(rust_snake::moderation::configured())
         .insert_resource(MenuState::default())
         .insert_resource(GameRules::default())
-        .insert_resource(WallQueue::default());
+        .insert_resource(WallQueue::default())
+        .insert_resource(AttractMode::default())
+        .insert_resource(Practice::default())
+        .insert_resource(HintSettings::default())
+        .insert_resource(KeyBindings::load())
+        .insert_resource(TouchSettings::load())
+        .insert_resource(ControlScheme::load())
+        .insert_resource(Leaderboard::new(leaderboard_config.backend()))
+        .insert_resource(leaderboard_config)
+        .insert_resource(LocalLeaderboard::load())
+        .insert_resource(LeaderboardStatus::default())
+        .insert_resource(Submission::default())
+        .insert_resource(CurrentRun::default());
 
     // States and Resources
     app.init_state::<GameState>()
@@ -62,7 +112,10 @@ fn main() {
 
     // Systems ----------------
     // Startup
-    app.add_systems(Startup, (init_scores, setup, setup_ui).chain());
+    app.add_systems(Startup, (setup, setup_ui).chain());
+
+    // Input
+    app.add_plugins(ActionsPlugin);
 
     // Update
     // -- Core
@@ -70,27 +123,85 @@ fn main() {
         Update,
         (
             food_spawner,
-            control_snake,
-            update_snake,
+            dpad_ui,
+            toggle_pause,
+            control_snake
+                .run_if(not(resource_exists::<ExternalControl>))
+                .run_if(not_paused),
+            update_snake.run_if(not_paused),
             snake_eating,
+            // queued on the eaten food's cell, as in `Simulation::step`
+            enqueue_walls,
             snake_growth,
+            try_spawn_walls,
             quick_speed,
             quick_reset,
+            (toggle_hints, update_hints).chain(),
             game_over,
             position_translation,
         )
             .chain()
             .run_if(in_state(GameState::Playing)),
     )
-    .add_systems(Update, game_over.run_if(in_state(GameState::GameOver)))
-    .add_systems(Update, enter_name.run_if(in_state(GameState::EnterName)))
-    .add_systems(Update, reset_game)
-    .add_systems(Update, enqueue_walls)
-    .add_systems(Update, try_spawn_walls.run_if(in_state(GameState::Playing)))
-    .add_systems(Update, game_rule_changer);
+    .add_systems(
+        Update,
+        (game_over, position_translation)
+            .chain()
+            .run_if(in_state(GameState::GameOver)),
+    )
+    .add_systems(OnExit(GameState::GameOver), clear_fatal_cell)
+    .add_systems(
+        Update,
+        enter_name
+            .after(enter_name_ui)
+            .after(calc_highscores)
+            .run_if(in_state(GameState::EnterName)),
+    )
+    .add_systems(Update, reset_game.after(game_rule_changer))
+    .add_systems(Update, game_rule_changer)
+    .add_systems(OnEnter(GameState::EnterName), prefill_name)
+    .add_systems(OnExit(GameState::Playing), (clear_hints, unpause));
+
+    // -- Bot
+    #[cfg(all(feature = "tools", not(target_arch = "wasm32")))]
+    if let Some(args) = bot_args {
+        use rust_snake::bot::*;
+        let bot = match BotProcess::spawn(&args.command, args.budget) {
+            Ok(bot) => bot,
+            Err(e) => {
+                eprintln!("Failed to start bot: {}", e);
+                std::process::exit(1);
+            }
+        };
+        app.insert_resource(BotController::new(bot))
+            .insert_resource(ExternalControl)
+            .add_systems(
+                Update,
+                (
+                    bot_control.after(control_snake).before(update_snake),
+                    bot_game_over.after(update_snake),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Playing), reset_bot);
+    }
+
+    // -- Attract mode
+    app.add_systems(
+        Update,
+        (attract_input, run_attract, position_translation)
+            .chain()
+            .run_if(in_state(GameState::MainMenu)),
+    )
+    .add_systems(OnExit(GameState::MainMenu), clear_attract);
 
     // -- UI
-    app.add_systems(Update, menu_ui.run_if(in_state(GameState::MainMenu)))
+    app.add_systems(
+        Update,
+        (menu_ui, controls_ui, leaderboard_browser_ui)
+            .chain()
+            .run_if(in_state(GameState::MainMenu)),
+    )
         .add_systems(Update, playing_ui.run_if(in_state(GameState::Playing)))
         .add_systems(Update, game_over_ui.run_if(in_state(GameState::GameOver)))
         .add_systems(
@@ -137,7 +248,7 @@ fn main() {
                 .chain(),
         ),
     )
-    .add_systems(Update, upload_scores);
+    .add_systems(Update, (sync_local_scores, upload_scores).chain());
 
     // plugins
     app.add_plugins(
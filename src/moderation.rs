@@ -0,0 +1,23 @@
+//! Player names, checked with the same [`Moderator`] the leaderboard server
+//! uses, extended on native by the file named in `RUST_SNAKE_BLOCKLIST`.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::prelude::*;
+
+pub use snake_core::moderation::*;
+
+/// The built-in list, plus the file named by [`BLOCKLIST_ENV`] on native if
+/// it can be read
+pub fn configured() -> Moderator {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(path) = std::env::var(BLOCKLIST_ENV) {
+        match Moderator::with_file(Path::new(&path)) {
+            Ok(moderator) => return moderator,
+            Err(e) => warn!("Couldn't read the blocklist {}: {}", path, e),
+        }
+    }
+    Moderator::builtin()
+}
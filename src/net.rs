@@ -0,0 +1,158 @@
+//! Talking to the leaderboard without ever panicking. Every request has a
+//! timeout, failures come back as a [`NetworkError`], and transient ones
+//! are retried with exponential backoff.
+
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use futures::FutureExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// How long a single request may take
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tries per request, including the first
+pub const ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled before each one after
+pub const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkError {
+    /// Networking is turned off
+    Offline,
+    /// The request couldn't be sent or the connection broke
+    Request(String),
+    /// No answer within [`REQUEST_TIMEOUT`]
+    Timeout,
+    /// The server answered with an error status
+    Status(u16),
+    /// The answer wasn't what the game expects
+    Parse(String),
+    /// The server refused a submission, saying why
+    Rejected(String),
+}
+
+impl NetworkError {
+    /// Whether trying again might help
+    pub fn is_transient(&self) -> bool {
+        match self {
+            NetworkError::Request(_) | NetworkError::Timeout => true,
+            NetworkError::Status(status) => *status >= 500 || *status == 429,
+            NetworkError::Offline
+            | NetworkError::Parse(_)
+            | NetworkError::Rejected(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Offline => write!(f, "networking is turned off"),
+            NetworkError::Request(e) => write!(f, "request failed: {}", e),
+            NetworkError::Timeout => write!(f, "the server took too long"),
+            NetworkError::Status(status) => {
+                write!(f, "the server answered {}", status)
+            }
+            NetworkError::Parse(e) => write!(f, "unexpected answer: {}", e),
+            NetworkError::Rejected(reason) => write!(f, "rejected: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+impl From<reqwest::Error> for NetworkError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            NetworkError::Timeout
+        } else if let Some(status) = e.status() {
+            NetworkError::Status(status.as_u16())
+        } else {
+            NetworkError::Request(e.to_string())
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Gives up on `request` after [`REQUEST_TIMEOUT`]
+pub async fn with_timeout<T>(
+    request: impl Future<Output = Result<T, NetworkError>>,
+) -> Result<T, NetworkError> {
+    futures::select! {
+        res = request.fuse() => res,
+        _ = sleep(REQUEST_TIMEOUT).fuse() => Err(NetworkError::Timeout),
+    }
+}
+
+/// Runs `request` up to [`ATTEMPTS`] times while it fails transiently
+pub async fn retry<T, F, Fut>(mut request: F) -> Result<T, NetworkError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, NetworkError>>,
+{
+    let mut backoff = FIRST_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match with_timeout(request()).await {
+            Err(e) if e.is_transient() && attempt < ATTEMPTS => {
+                bevy::log::debug!(
+                    "attempt {} failed, retrying: {}",
+                    attempt,
+                    e
+                );
+                sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Fetches and decodes JSON from `url`
+pub async fn get_json<T: DeserializeOwned>(
+    url: &str,
+) -> Result<T, NetworkError> {
+    retry(|| async {
+        let res = reqwest::Client::new()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?;
+        let text = res.text().await?;
+        serde_json::from_str(&text)
+            .map_err(|e| NetworkError::Parse(e.to_string()))
+    })
+    .await
+}
+
+/// Posts `body` as JSON to `url`. A 400 or 422 answer means the server
+/// looked at the body and refused it, its text says why.
+pub async fn post_json<T: Serialize>(
+    url: &str,
+    body: &T,
+) -> Result<(), NetworkError> {
+    retry(|| async {
+        let res = reqwest::Client::new().post(url).json(body).send().await?;
+        if matches!(res.status().as_u16(), 400 | 422) {
+            let reason = res.text().await.unwrap_or_default();
+            return Err(NetworkError::Rejected(reason));
+        }
+        res.error_for_status()?;
+        Ok(())
+    })
+    .await
+}
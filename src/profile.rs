@@ -0,0 +1,58 @@
+//! The player on this device. A profile is made the first time the game
+//! runs, with a random ID that goes with every score. Boards keep one entry
+//! per ID and mode, so a player who changes their name keeps their place.
+
+use bevy::prelude::*;
+
+const PROFILE_KEY: &str = "profile";
+
+#[derive(Resource, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PlayerProfile {
+    /// Random hex, never shown and never changed
+    pub id: String,
+    /// The name last entered, filled in on the name screen
+    pub name: String,
+    /// Submit under `name` without asking
+    pub skip_name_entry: bool,
+}
+
+impl Default for PlayerProfile {
+    fn default() -> Self {
+        Self {
+            id: new_id(),
+            name: String::new(),
+            skip_name_entry: false,
+        }
+    }
+}
+
+fn new_id() -> String {
+    format!(
+        "{:032x}{:032x}",
+        rand::random::<u128>(),
+        rand::random::<u128>()
+    )
+}
+
+impl PlayerProfile {
+    /// The saved profile, or a new one saved straight away so its ID sticks
+    pub fn load() -> Self {
+        let mut profile: Self =
+            crate::storage::load_json(PROFILE_KEY).unwrap_or_default();
+        if profile.id.is_empty() {
+            profile.id = new_id();
+        }
+        profile.save();
+        profile
+    }
+
+    pub fn save(&self) {
+        crate::storage::save_json(PROFILE_KEY, self);
+    }
+
+    /// Whether the name screen can be skipped
+    pub fn skips_name_entry(&self) -> bool {
+        self.skip_name_entry && !self.name.is_empty()
+    }
+}
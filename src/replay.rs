@@ -0,0 +1,72 @@
+//! The run being played, recorded as a [`Replay`] as it goes.
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::game_mode::GameRules;
+use crate::score::RunStats;
+use crate::snake::Direction;
+
+pub use snake_core::replay::*;
+
+/// The run being played. Food is drawn from its rng in the same order a
+/// [`Simulation`] draws it, and every tick is recorded, so the finished
+/// run can be submitted with its [`Replay`].
+#[derive(Resource, Debug, Clone)]
+pub struct CurrentRun {
+    pub replay: Replay,
+    rng: StdRng,
+    heading: Direction,
+    /// How the run ended, once it has
+    pub death: Option<crate::GameOverEvent>,
+}
+
+impl Default for CurrentRun {
+    fn default() -> Self {
+        Self::new(0, GameRules::default())
+    }
+}
+
+impl CurrentRun {
+    pub fn new(seed: u64, rules: GameRules) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let heading = rng.gen::<u8>().into();
+        Self {
+            replay: Replay::new(seed, rules),
+            rng,
+            heading,
+            death: None,
+        }
+    }
+
+    /// The way the snake faces when the run starts
+    pub fn heading(&self) -> Direction {
+        self.heading
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Notes that the run has ended, returning whether this is news. Only
+    /// the first death counts, so a tick that ends the run more than one
+    /// way, or a second report of it, is dropped.
+    pub fn end(&mut self, death: crate::GameOverEvent) -> bool {
+        if self.death.is_some() {
+            return false;
+        }
+        self.death = Some(death);
+        true
+    }
+
+    /// How the run went, from its replay, with the death the game saw.
+    /// `None` if the replay doesn't play back.
+    pub fn stats(&self) -> Option<RunStats> {
+        let mut stats = self.replay.stats().ok()?;
+        if let Some(death) = self.death {
+            stats.death = Some(death.cause);
+        }
+        Some(stats)
+    }
+}
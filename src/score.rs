@@ -1,10 +1,36 @@
-use std::sync::{Arc, Mutex, OnceLock};
-
 use bevy::prelude::*;
 use bevy::utils::tracing;
-use futures::FutureExt;
 
-pub static HIGHSCORES: OnceLock<Arc<Mutex<Highscores>>> = OnceLock::new();
+use crate::game_mode::GameRules;
+use crate::leaderboard::{BackendReply, Leaderboard, LocalLeaderboard};
+use crate::net::NetworkError;
+
+pub use snake_core::score::*;
+
+/// How the last download of the online board went
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub enum LeaderboardStatus {
+    #[default]
+    NotLoaded,
+    Loading,
+    Loaded {
+        at: bevy::utils::Instant,
+    },
+    Failed(NetworkError),
+}
+
+/// How the run just entered on the name screen fared with the online board
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub enum Submission {
+    #[default]
+    Idle,
+    Sending(Highscore),
+    Accepted,
+    /// The board refused the run, saying why
+    Rejected(String),
+    /// The board couldn't be reached, the run stays pending
+    Failed(NetworkError),
+}
 
 #[derive(States, Clone, Eq, PartialEq, Debug, Hash, Default)]
 pub enum ScoresDownloaded {
@@ -22,27 +48,26 @@ pub struct TriggerDownload;
 #[derive(Debug, Clone, Event)]
 pub struct SendHighscores(pub Highscore);
 
+/// Whether the last run made it into the [`TOP_PLACES`]
 #[derive(Debug, Resource)]
 pub enum LeaderboardEarned {
-    Placed(u8),
+    /// The place it took, starting at 1
+    Placed(usize),
     NotPlaced,
 }
 
-#[derive(Resource, Clone, Default)]
-pub struct Score(pub u32);
-
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct Highscore {
-    pub name: String,
-    pub score: u32,
+impl LeaderboardEarned {
+    pub fn for_rank(rank: usize) -> Self {
+        if rank <= TOP_PLACES {
+            LeaderboardEarned::Placed(rank)
+        } else {
+            LeaderboardEarned::NotPlaced
+        }
+    }
 }
 
-#[derive(
-    Resource, Clone, Debug, serde::Serialize, serde::Deserialize, Default,
-)]
-pub struct Highscores {
-    pub highscores: Vec<Highscore>,
-}
+#[derive(Resource, Clone, Default)]
+pub struct Score(pub u32);
 
 pub fn download_manager(
     mut scores_downloaded: ResMut<NextState<ScoresDownloaded>>,
@@ -55,67 +80,103 @@ pub fn download_manager(
     }
 }
 
-pub fn init_scores() {
-    HIGHSCORES.get_or_init(|| Arc::new(Mutex::new(Highscores::default())));
-}
-
 pub fn download_scores(
     mut trigger_download: EventReader<TriggerDownload>,
+    mut status: ResMut<LeaderboardStatus>,
+    leaderboard: Res<Leaderboard>,
 ) {
-    
-    if trigger_download.read().next().is_some() {
-        crate::run_async(async move {
-            let mut fut = tokio::task::spawn_local(async {
-                let client = reqwest::Client::new();
-                let res = client
-                    .get("https://berintmoffett.com/api/snake-highscores")
-                    .send()
-                    .await
-                    .unwrap();
-                let text = res.text().await.unwrap();
-                let highscores = serde_json::from_str::<Highscores>(&text);
-                match highscores {
-                    Ok(v) => v,
-                    Err(e) => panic!("Error: {:#?}", e),
-                }
-            })
-                .fuse();
-
-            futures::select! {
-                res = fut => {
-                    let hs_arc = HIGHSCORES.get();
-                    let mut highscores = hs_arc.unwrap().lock().unwrap();
-                    *highscores = res.unwrap();
-                }
-            }
-        });
+    if trigger_download.read().next().is_none() {
+        return;
+    }
+    let mut online = false;
+    for rules in GameRules::ALL {
+        online |= leaderboard.fetch(rules.name());
     }
+    if online {
+        *status = LeaderboardStatus::Loading;
+    } else {
+        *status = LeaderboardStatus::Failed(NetworkError::Offline);
+    }
+}
 
+pub fn upload_scores(
+    mut send_highscores: EventReader<SendHighscores>,
+    mut local: ResMut<LocalLeaderboard>,
+    leaderboard: Res<Leaderboard>,
+) {
+    for ev in send_highscores.read() {
+        if !leaderboard.is_online() {
+            return;
+        }
+        if !local.mark_sending(&ev.0) {
+            continue;
+        }
+        debug!("{:?}", serde_json::to_string(&ev.0));
+        leaderboard.submit(ev.0.clone());
+    }
 }
 
-pub fn upload_scores(mut send_highscores: EventReader<SendHighscores>) {
-    if let Some(ev) = send_highscores.read().next() {
-        let highscore = ev.0.clone();
-        debug!("{:?}", serde_json::to_string(&highscore).unwrap());
-        crate::run_async(async move {
-            let mut fut = tokio::task::spawn_local(async move {
-                let client = reqwest::Client::new();
-                let _ = client
-                    .post("https://berintmoffett.com/api/snake-highscores")
-                    .json(&highscore)
-                    .send()
-                    .await
-                    .unwrap();
-            })
-            .fuse();
-
-            futures::select! {
-                _ = fut => tracing::debug!("Highscores uploaded"),
+/// Picks up finished requests. Downloads update the [`LeaderboardStatus`],
+/// the run's place on the whole board replaces the guess made from the top,
+/// accepted or rejected submissions stop being pending, and whenever the
+/// board answers, runs it hasn't seen yet are sent again.
+pub fn sync_local_scores(
+    mut leaderboard: ResMut<Leaderboard>,
+    mut local: ResMut<LocalLeaderboard>,
+    mut status: ResMut<LeaderboardStatus>,
+    mut submission: ResMut<Submission>,
+    mut earned: ResMut<LeaderboardEarned>,
+    mut send_highscores: EventWriter<SendHighscores>,
+) {
+    for reply in leaderboard.replies() {
+        match reply {
+            BackendReply::Fetched(mode, Ok(top)) => {
+                leaderboard.set_top(&mode, top);
+                *status = LeaderboardStatus::Loaded {
+                    at: bevy::utils::Instant::now(),
+                };
+                for highscore in local.unsent() {
+                    send_highscores.send(SendHighscores(highscore));
+                }
             }
-        });
+            BackendReply::Fetched(_, Err(e)) => {
+                tracing::warn!("Couldn't download highscores: {}", e);
+                *status = LeaderboardStatus::Failed(e);
+            }
+            BackendReply::Standing(mode, Ok(standing)) => {
+                // the whole board knows better than the top we have
+                *earned = LeaderboardEarned::for_rank(standing.rank);
+                leaderboard.set_standing(&mode, standing);
+            }
+            BackendReply::Standing(_, Err(e)) => {
+                tracing::warn!("Couldn't look up the run's place: {}", e);
+                leaderboard.rank_failed();
+            }
+            BackendReply::Browsed(query, page) => {
+                leaderboard.set_page(query, page);
+            }
+            BackendReply::Submitted(highscore, res) => {
+                match &res {
+                    Ok(()) => tracing::debug!("Highscore uploaded"),
+                    Err(e) => {
+                        tracing::warn!("Couldn't upload highscore: {}", e)
+                    }
+                }
+                local.submitted(&highscore, &res);
+
+                if *submission == Submission::Sending(highscore) {
+                    *submission = match res {
+                        Ok(()) => Submission::Accepted,
+                        Err(NetworkError::Rejected(reason)) => {
+                            Submission::Rejected(reason)
+                        }
+                        Err(e) => Submission::Failed(e),
+                    };
+                }
+            }
+        }
     }
 }
 
 #[derive(Component)]
 pub struct ScoreText;
-
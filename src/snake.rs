@@ -1,60 +1,45 @@
-use crate::{cheats::ScoreBlocker, game_mode::{GameRules, Wall}, Position};
+use crate::{
+    cheats::ScoreBlocker,
+    game_mode::{GameRules, Wall},
+    input::{Action, ActionState, ControlScheme, ControlSchemeExt},
+    Position,
+};
 use bevy::prelude::*;
 
-use std::collections::VecDeque;
+pub use snake_core::snake::Direction;
 
-#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Direction {
-    fn opposite(self) -> Self {
-        match self {
-            Self::Left => Self::Right,
-            Self::Right => Self::Left,
-            Self::Up => Self::Down,
-            Self::Down => Self::Up,
-        }
-    }
-}
+#[derive(Resource, Default)]
+pub struct LastPressed(pub Direction);
 
-impl Default for Direction {
-    fn default() -> Self {
-        Self::Up
+/// Turns the player has made that the snake hasn't yet, see
+/// [`InputBuffer`](crate::sim::InputBuffer)
+#[derive(Resource, Default)]
+pub struct InputQueue(pub crate::sim::InputBuffer);
+
+impl InputQueue {
+    /// A buffer with the depth saved in the settings
+    pub fn load() -> Self {
+        let depth = crate::storage::load_json(INPUT_DEPTH_KEY)
+            .unwrap_or(crate::sim::DEFAULT_INPUT_DEPTH);
+        Self(crate::sim::InputBuffer::new(depth))
     }
-}
 
-impl From<u8> for Direction {
-    fn from(val: u8) -> Self {
-        let bound = val % 4;
-        if bound == 0 {
-            Direction::Up
-        } else if bound == 1 {
-            Direction::Right
-        } else if bound == 2 {
-            Direction::Down
-        } else {
-            Direction::Left
-        }
+    pub fn save(&self) {
+        crate::storage::save_json(INPUT_DEPTH_KEY, &self.0.depth());
     }
 }
 
-#[derive(Resource, Default)]
-pub struct LastPressed(pub Direction);
-
-#[derive(Resource, Default)]
-pub struct InputQueue(pub VecDeque<Direction>);
-
-#[derive(Resource, Default)]
-pub struct InputQueueTimer(pub Timer);
+/// Storage key the input buffer depth is saved under
+pub const INPUT_DEPTH_KEY: &str = "input_depth";
 
 #[derive(Resource, Default)]
 pub struct TickAccum(pub f32);
 
+/// Present when something other than the keyboard steers the snake, which
+/// turns off [`control_snake`] and keeps runs off the leaderboard
+#[derive(Resource)]
+pub struct ExternalControl;
+
 #[derive(Component)]
 pub struct SnakeHead {
     pub rot: Direction,
@@ -91,21 +76,20 @@ pub fn add_snake(
         })
         .insert(SnakeHead { rot: dir })
         .insert(Segment)
-        .insert(Position { x: 10, y: 10 })
+        .insert(crate::sim::START)
         .insert(crate::Size::square(crate::BLOCK_SIZE))
         .id()]);
-    let tail_pos = match dir {
-        Direction::Up => Position { x: 10, y: 9 },
-        Direction::Down => Position { x: 10, y: 11 },
-        Direction::Left => Position { x: 11, y: 10 },
-        Direction::Right => Position { x: 9, y: 10 },
-    };
-    *last_tail_position = LastTailPosition(Some(tail_pos));
+    *last_tail_position =
+        LastTailPosition(Some(crate::sim::start_tail(dir)));
 }
 
+/// Eats food the head has just moved onto. A move that ended the game
+/// doesn't eat, even if food lay on the fatal cell, the same as in
+/// [`crate::sim::Simulation`].
 pub fn snake_eating(
     mut commands: Commands,
     mut eat_writer: EventWriter<EatEvent>,
+    mut game_over_reader: EventReader<crate::GameOverEvent>,
     food_positions: Query<(Entity, &Position), With<crate::food::Food>>,
     head_positions: Query<&Position, With<SnakeHead>>,
     mut score: ResMut<crate::score::Score>,
@@ -113,6 +97,9 @@ pub fn snake_eating(
     mut tick_accum: ResMut<TickAccum>,
     score_blocker: Res<ScoreBlocker>,
 ) {
+    if game_over_reader.read().count() > 0 {
+        return;
+    }
     for head_pos in head_positions.iter() {
         for (ent, food_pos) in food_positions.iter() {
             if food_pos == head_pos {
@@ -123,11 +110,8 @@ pub fn snake_eating(
                     return;
                 }
 
-                if score.0 % 10 == 0 {
-                    tick_accum.0 += crate::BIG_TICK_INCREASE;
-                } else {
-                    tick_accum.0 += crate::TICK_INCREASE;
-                }
+                tick_accum.0 =
+                    crate::sim::next_tick_rate(tick_accum.0, score.0);
 
                 tick_timer.0 = Timer::from_seconds(
                     1. / tick_accum.0,
@@ -160,48 +144,38 @@ pub fn update_snake(
     mut input_queue: ResMut<InputQueue>,
     walls: Query<Entity, With<Wall>>,
     game_rules: Res<GameRules>,
+    mut run: ResMut<crate::replay::CurrentRun>,
 ) {
     if timer.0.tick(time.delta()).just_finished() {
         if let Some((head_entity, mut head)) = heads.iter_mut().next() {
-            if let Some(dir) = input_queue.0.pop_front() {
-                if dir != head.rot.opposite() {
-                    head.rot = dir;
-                }
-            }
+            let input = input_queue.0.pop();
+            run.replay.record(input);
+            head.rot = crate::sim::turn(head.rot, input);
 
             let segment_positions = segments
                 .iter()
                 .map(|e| *positions.get_mut(*e).unwrap())
                 .collect::<Vec<Position>>();
+            let wall_positions = walls
+                .iter()
+                .map(|e| *positions.get(e).unwrap())
+                .collect::<Vec<Position>>();
             let mut head_pos = positions.get_mut(head_entity).unwrap();
-
-            match &head.rot {
-                Direction::Up => head_pos.y += 1,
-                Direction::Right => head_pos.x += 1,
-                Direction::Down => head_pos.y -= 1,
-                Direction::Left => head_pos.x -= 1,
-            };
-
-            if head_pos.x < 0
-                || head_pos.x > crate::WALL as i32
-                || head_pos.y < 0
-                || head_pos.y > crate::WALL as i32
-            {
-                game_over_writer.send(crate::GameOverEvent);
-            }
-
-            if segment_positions.contains(&head_pos) {
-                game_over_writer.send(crate::GameOverEvent);
-            }
-
-            // shadow mutable borrow with immutable borrow
-            let head_pos = positions.get(head_entity).unwrap();
-            if game_rules.do_collide_walls {
-                for wall_ent in walls.iter() {
-                    let wall_pos = positions.get(wall_ent).unwrap();
-                    if *head_pos == *wall_pos {
-                        game_over_writer.send(crate::GameOverEvent);
-                    }
+            *head_pos = crate::sim::advance(*head_pos, head.rot);
+
+            if let Some(cause) = crate::sim::check_collision(
+                *head_pos,
+                &segment_positions,
+                &wall_positions,
+                &game_rules,
+            ) {
+                let death = crate::GameOverEvent {
+                    cause,
+                    cell: *head_pos,
+                    tick: run.replay.ticks,
+                };
+                if run.end(death) {
+                    game_over_writer.send(death);
                 }
             }
 
@@ -218,44 +192,34 @@ pub fn update_snake(
     }
 }
 
+/// Queues the player's turns, every one pressed this frame so a quick
+/// U-turn isn't lost. [`update_snake`] makes one per tick.
 pub fn control_snake(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
+    scheme: Res<ControlScheme>,
+    heads: Query<&SnakeHead>,
     mut input_queue: ResMut<InputQueue>,
-    mut input_timer: ResMut<InputQueueTimer>,
-    time: Res<Time>,
 ) {
-    let dir = if keyboard_input.just_pressed(KeyCode::ArrowUp)
-        || keyboard_input.just_pressed(KeyCode::KeyW)
-    {
-        Direction::Up
-    } else if keyboard_input.just_pressed(KeyCode::ArrowRight)
-        || keyboard_input.just_pressed(KeyCode::KeyD)
-    {
-        Direction::Right
-    } else if keyboard_input.just_pressed(KeyCode::ArrowDown)
-        || keyboard_input.just_pressed(KeyCode::KeyS)
-    {
-        Direction::Down
-    } else if keyboard_input.just_pressed(KeyCode::ArrowLeft)
-        || keyboard_input.just_pressed(KeyCode::KeyA)
-    {
-        Direction::Left
-    } else {
-        if input_timer.0.finished() {
-            input_queue.0.clear();
-        }
+    let Some(head) = heads.iter().next() else {
         return;
     };
-
-    input_timer.0.tick(time.delta());
-
-    if input_timer.0.finished() {
-        input_timer.0.reset();
-        input_queue.0.clear();
-        input_queue.0.push_back(dir);
-    } else {
-        input_timer.0.reset();
-        input_queue.0.push_back(dir);
+    let mut pressed = Action::ALL
+        .into_iter()
+        .filter(|action| actions.just_pressed(*action))
+        .collect::<Vec<_>>();
+    // keys pressed in the same frame come in no particular order, so turns
+    // that couldn't be queued yet are tried again after the others
+    loop {
+        let before = pressed.len();
+        pressed.retain(|action| {
+            let heading = input_queue.0.heading_after(head.rot);
+            scheme
+                .turn(*action, heading)
+                .is_some_and(|dir| !input_queue.0.push(dir, head.rot))
+        });
+        if pressed.is_empty() || pressed.len() == before {
+            break;
+        }
     }
 }
 
@@ -0,0 +1,73 @@
+//! Small key/value persistence for settings and local data. On native each
+//! key is a JSON file in the user's config directory, on the web it lives in
+//! the browser's localStorage.
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Overrides where native builds keep their files
+pub const CONFIG_DIR_ENV: &str = "RUST_SNAKE_CONFIG_DIR";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> Option<std::path::PathBuf> {
+    let dir = match std::env::var_os(CONFIG_DIR_ENV) {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => dirs::config_dir()?.join("rust-snake"),
+    };
+    Some(dir.join(format!("{}.json", key)))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(key: &str, value: &str) -> Result<(), String> {
+    let path = path(key).ok_or("no config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, value).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("rust-snake.{}", key))
+        .ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(key: &str, value: &str) -> Result<(), String> {
+    let storage = local_storage().ok_or("localStorage is unavailable")?;
+    storage
+        .set_item(&format!("rust-snake.{}", key), value)
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Loads and parses a value, `None` if it's missing or unreadable
+pub fn load_json<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let text = load(key)?;
+    match serde_json::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Ignoring unreadable {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Saves a value, logging rather than failing if it can't be written
+pub fn save_json<T: Serialize>(key: &str, value: &T) {
+    let text = serde_json::to_string(value).expect("value serializes");
+    if let Err(e) = save(key, &text) {
+        error!("Failed to save {}: {}", key, e);
+    }
+}
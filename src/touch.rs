@@ -0,0 +1,338 @@
+//! Touch screen controls for phones and tablets: swipes steer the snake and
+//! an optional on-screen d-pad does the same with taps.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::egui::{self, FontId, RichText};
+use bevy_egui::EguiContexts;
+
+use crate::input::{Action, ActionState, ControlScheme};
+use crate::snake::Direction;
+
+const SETTINGS_KEY: &str = "touch_settings";
+
+/// How touches are read, changeable from the controls menu and persisted
+#[derive(
+    Resource, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(default)]
+pub struct TouchSettings {
+    /// Logical pixels a finger has to travel to count as a swipe
+    pub swipe_distance: f32,
+    /// Seconds a swipe may take, slower drags are ignored
+    pub swipe_time: f32,
+    /// Show the on-screen d-pad while playing on a touch screen
+    pub show_dpad: bool,
+}
+
+impl Default for TouchSettings {
+    fn default() -> Self {
+        Self {
+            swipe_distance: 30.,
+            swipe_time: 0.5,
+            show_dpad: true,
+        }
+    }
+}
+
+impl TouchSettings {
+    pub fn load() -> Self {
+        crate::storage::load_json(SETTINGS_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        crate::storage::save_json(SETTINGS_KEY, self);
+    }
+}
+
+/// Where and when each finger currently on the screen went down. Set to
+/// `None` once that finger has swiped, so one gesture is one turn.
+#[derive(Resource, Debug, Default)]
+pub struct SwipeTracker(HashMap<u64, Option<(Vec2, f32)>>);
+
+/// Whether the player has touched the screen, which brings up the d-pad
+/// and the on-screen keyboard
+#[derive(Resource, Debug, Default)]
+pub struct TouchInUse(pub bool);
+
+/// The direction of a swipe from `start` to `end`, if it went far enough.
+/// Screen coordinates grow downwards.
+pub fn swipe_direction(
+    start: Vec2,
+    end: Vec2,
+    distance: f32,
+) -> Option<Direction> {
+    let delta = end - start;
+    if delta.length() < distance {
+        None
+    } else if delta.x.abs() > delta.y.abs() {
+        Some(if delta.x > 0. {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if delta.y > 0. {
+            Direction::Down
+        } else {
+            Direction::Up
+        })
+    }
+}
+
+/// The action a swipe stands for. Relative steering turns with sideways
+/// swipes, and with one button any swipe turns.
+fn swipe_action(dir: Direction, scheme: ControlScheme) -> Option<Action> {
+    match (scheme, dir) {
+        (ControlScheme::Absolute, Direction::Up) => Some(Action::TurnUp),
+        (ControlScheme::Absolute, Direction::Down) => Some(Action::TurnDown),
+        (ControlScheme::Absolute, Direction::Left) => Some(Action::TurnLeft),
+        (ControlScheme::Absolute, Direction::Right) => Some(Action::TurnRight),
+        (ControlScheme::Relative, Direction::Left) => {
+            Some(Action::TurnCounterClockwise)
+        }
+        (ControlScheme::Relative, Direction::Right) => {
+            Some(Action::TurnClockwise)
+        }
+        (ControlScheme::Relative, _) => None,
+        (ControlScheme::OneButton, _) => Some(Action::TurnClockwise),
+    }
+}
+
+/// Turns swipes into actions. Any new touch also counts as "any key", so
+/// the game over and leaderboard screens can be tapped through.
+pub fn touch_actions(
+    touches: Res<Touches>,
+    time: Res<Time>,
+    settings: Res<TouchSettings>,
+    scheme: Res<ControlScheme>,
+    mut tracker: ResMut<SwipeTracker>,
+    mut in_use: ResMut<TouchInUse>,
+    mut actions: ResMut<ActionState>,
+) {
+    let now = time.elapsed_seconds();
+    for touch in touches.iter_just_pressed() {
+        tracker.0.insert(touch.id(), Some((touch.position(), now)));
+        actions.mark_any_just_pressed();
+        in_use.0 = true;
+    }
+    if touches.iter().next().is_some() {
+        actions.mark_any_pressed();
+    }
+
+    for touch in touches.iter() {
+        let Some(start) = tracker.0.get_mut(&touch.id()) else {
+            continue;
+        };
+        let Some((position, started)) = *start else {
+            continue;
+        };
+        if now - started > settings.swipe_time {
+            *start = None;
+            continue;
+        }
+        let swipe = swipe_direction(
+            position,
+            touch.position(),
+            settings.swipe_distance,
+        );
+        if let Some(dir) = swipe {
+            if let Some(action) = swipe_action(dir, *scheme) {
+                actions.press(action);
+            }
+            *start = None;
+        }
+    }
+
+    for touch in touches
+        .iter_just_released()
+        .chain(touches.iter_just_canceled())
+    {
+        tracker.0.remove(&touch.id());
+    }
+}
+
+/// Big arrow buttons in the bottom corner while playing on a touch screen,
+/// or turn buttons for the relative and one-button schemes
+pub fn dpad_ui(
+    settings: Res<TouchSettings>,
+    scheme: Res<ControlScheme>,
+    in_use: Res<TouchInUse>,
+    mut actions: ResMut<ActionState>,
+    mut contexts: EguiContexts,
+) {
+    if !settings.show_dpad || !in_use.0 {
+        return;
+    }
+
+    let size = egui::Vec2::splat(64.0);
+    let arrow = |ui: &mut egui::Ui, text: &str| {
+        ui.add_sized(
+            size,
+            egui::Button::new(
+                RichText::new(text).font(FontId::proportional(36.0)),
+            ),
+        )
+        .clicked()
+    };
+    egui::Area::new(egui::Id::new("dpad"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-16.0, -16.0))
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("dpad_grid")
+                .spacing([4.0, 4.0])
+                .show(ui, |ui| match *scheme {
+                    ControlScheme::Absolute => {
+                        ui.label("");
+                        if arrow(ui, "^") {
+                            actions.press(Action::TurnUp);
+                        }
+                        ui.label("");
+                        ui.end_row();
+
+                        if arrow(ui, "<") {
+                            actions.press(Action::TurnLeft);
+                        }
+                        if arrow(ui, "II") {
+                            actions.press(Action::Pause);
+                        }
+                        if arrow(ui, ">") {
+                            actions.press(Action::TurnRight);
+                        }
+                        ui.end_row();
+
+                        ui.label("");
+                        if arrow(ui, "v") {
+                            actions.press(Action::TurnDown);
+                        }
+                        ui.label("");
+                        ui.end_row();
+                    }
+                    ControlScheme::Relative => {
+                        if arrow(ui, "⟲") {
+                            actions.press(Action::TurnCounterClockwise);
+                        }
+                        if arrow(ui, "II") {
+                            actions.press(Action::Pause);
+                        }
+                        if arrow(ui, "⟳") {
+                            actions.press(Action::TurnClockwise);
+                        }
+                    }
+                    ControlScheme::OneButton => {
+                        if arrow(ui, "II") {
+                            actions.press(Action::Pause);
+                        }
+                        if arrow(ui, "⟳") {
+                            actions.press(Action::TurnClockwise);
+                        }
+                    }
+                });
+        });
+}
+
+/// Letters and digits to tap when there is no hardware keyboard
+pub fn on_screen_keyboard(ui: &mut egui::Ui, name: &mut String) -> bool {
+    const ROWS: [&str; 4] =
+        ["1234567890", "QWERTYUIOP", "ASDFGHJKL", "ZXCVBNM"];
+
+    let size = egui::Vec2::new(40.0, 48.0);
+    let key = |ui: &mut egui::Ui, text: &str, width: f32| {
+        ui.add_sized(
+            egui::Vec2::new(width, size.y),
+            egui::Button::new(
+                RichText::new(text).font(FontId::proportional(24.0)),
+            ),
+        )
+        .clicked()
+    };
+
+    let mut submitted = false;
+    for row in ROWS {
+        ui.horizontal(|ui| {
+            // egui has no centered horizontal layout, so pad by hand
+            let gap = ui.spacing().item_spacing.x;
+            let row_width = row.len() as f32 * (size.x + gap);
+            ui.add_space((ui.available_width() - row_width).max(0.0) / 2.0);
+            for c in row.chars() {
+                if key(ui, &c.to_string(), size.x)
+                    && name.len() < crate::moderation::MAX_NAME_LEN
+                {
+                    name.push(c);
+                }
+            }
+        });
+    }
+    ui.horizontal(|ui| {
+        let gap = ui.spacing().item_spacing.x;
+        let row_width = 2.0 * (size.x * 3.0 + gap);
+        ui.add_space((ui.available_width() - row_width).max(0.0) / 2.0);
+        if key(ui, "Delete", size.x * 3.0) {
+            name.pop();
+        }
+        if key(ui, "OK", size.x * 3.0) {
+            submitted = true;
+        }
+    });
+    submitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swipe(x: f32, y: f32) -> Option<Direction> {
+        swipe_direction(
+            Vec2::new(100., 100.),
+            Vec2::new(100. + x, 100. + y),
+            30.,
+        )
+    }
+
+    #[test]
+    fn short_drags_are_not_swipes() {
+        assert_eq!(swipe(0., 0.), None);
+        assert_eq!(swipe(29., 0.), None);
+        assert_eq!(swipe(-20., 20.), None);
+        assert_eq!(swipe(30., 0.), Some(Direction::Right));
+    }
+
+    #[test]
+    fn swipes_follow_the_longer_axis() {
+        assert_eq!(swipe(40., 10.), Some(Direction::Right));
+        assert_eq!(swipe(-40., -10.), Some(Direction::Left));
+        // window y grows downwards
+        assert_eq!(swipe(10., 40.), Some(Direction::Down));
+        assert_eq!(swipe(-10., -40.), Some(Direction::Up));
+    }
+
+    #[test]
+    fn exact_diagonals_go_up_or_down() {
+        assert_eq!(swipe(30., 30.), Some(Direction::Down));
+        assert_eq!(swipe(-30., -30.), Some(Direction::Up));
+    }
+
+    #[test]
+    fn swipes_map_to_each_scheme() {
+        use ControlScheme::*;
+        assert_eq!(swipe_action(Direction::Up, Absolute), Some(Action::TurnUp));
+        assert_eq!(
+            swipe_action(Direction::Left, Absolute),
+            Some(Action::TurnLeft)
+        );
+        assert_eq!(
+            swipe_action(Direction::Left, Relative),
+            Some(Action::TurnCounterClockwise)
+        );
+        assert_eq!(
+            swipe_action(Direction::Right, Relative),
+            Some(Action::TurnClockwise)
+        );
+        assert_eq!(swipe_action(Direction::Up, Relative), None);
+        for dir in Direction::ALL {
+            assert_eq!(
+                swipe_action(dir, OneButton),
+                Some(Action::TurnClockwise)
+            );
+        }
+    }
+}
@@ -1,6 +1,16 @@
-use crate::game_mode::{GameRuleChange, GameRules};
-use crate::score::{LeaderboardEarned, Score, HIGHSCORES};
-use crate::{GameState, ResetEvent};
+use crate::game_mode::{GameRuleChange, GameRules, Practice};
+use crate::score::{
+    AcquireHighscores, BoardQuery, Highscore, LeaderboardEarned,
+    LeaderboardStatus, Score, Submission, TimeRange, TOP_PLACES,
+};
+use crate::input::{
+    Action, ActionState, ControlScheme, ControlSchemeExt, KeyBindings, Paused,
+};
+use crate::leaderboard::{Leaderboard, LocalLeaderboard};
+use crate::profile::PlayerProfile;
+use crate::replay::CurrentRun;
+use crate::touch::{TouchInUse, TouchSettings};
+use crate::{GameState, ResetEvent, ViewLeaderboardEvent};
 use bevy::prelude::*;
 use bevy_egui::egui::RichText;
 use bevy_egui::egui::{self, FontId};
@@ -11,14 +21,31 @@ use crate::debug::{DebugStats, FrameRate};
 
 #[derive(Resource, Default)]
 pub struct MenuState {
-    leaderboard_confirmation_shown: bool,
+    browser_shown: bool,
+    /// What the leaderboard browser is showing
+    browser: BoardQuery,
+    controls_shown: bool,
+    rebinding: Option<Action>,
+    binding_error: Option<String>,
+    /// The board picked on the leaderboard screen, the last run's mode
+    /// until another is picked
+    leaderboard_mode: Option<GameRules>,
 }
 
 pub fn setup_ui(mut contexts: EguiContexts) {
-    contexts.ctx_mut().set_visuals(egui::Visuals {
+    let ctx = contexts.ctx_mut();
+    ctx.set_visuals(egui::Visuals {
         panel_fill: egui::Color32::TRANSPARENT,
         ..default()
     });
+    // big enough to hit with a finger
+    ctx.style_mut(|style| {
+        style.spacing.interact_size.y = 44.0;
+        style.spacing.button_padding = egui::Vec2::new(12.0, 6.0);
+        style.spacing.item_spacing = egui::Vec2::new(8.0, 8.0);
+        style.spacing.icon_width = 24.0;
+        style.spacing.icon_width_inner = 14.0;
+    });
 }
 
 pub fn menu_ui(
@@ -26,6 +53,9 @@ pub fn menu_ui(
     mut reset_event: EventWriter<ResetEvent>,
     mut game_rule_event: EventWriter<GameRuleChange>,
     mut menu_state: ResMut<MenuState>,
+    mut practice: ResMut<Practice>,
+    mut profile: ResMut<PlayerProfile>,
+    mut leaderboard: ResMut<Leaderboard>,
     mut contexts: EguiContexts,
 ) {
     let ctx = contexts.ctx_mut();
@@ -45,67 +75,336 @@ pub fn menu_ui(
                 RichText::new("View Leaderboard")
                     .font(FontId::proportional(30.0)),
             );
+            let controls_button = ui.button(
+                RichText::new("Controls").font(FontId::proportional(30.0)),
+            );
+            if controls_button.clicked() {
+                menu_state.controls_shown = true;
+            }
+            ui.checkbox(
+                &mut practice.0,
+                RichText::new("Practice (hints, unranked)")
+                    .font(FontId::proportional(20.0)),
+            );
+            if !profile.name.is_empty() {
+                let skip = RichText::new(format!(
+                    "Always play as {}, skip name entry",
+                    profile.name
+                ))
+                .font(FontId::proportional(20.0));
+                if ui.checkbox(&mut profile.skip_name_entry, skip).changed() {
+                    profile.save();
+                }
+            }
             if play_button.clicked() {
+                game_rule_event.send(GameRuleChange(GameRules::CLASSIC));
                 next_state.set(GameState::Playing);
                 reset_event.send(ResetEvent);
             }
 
             if walls_button.clicked() {
-                game_rule_event.send(GameRuleChange(GameRules {
-                    do_collide_walls: true,
-                    do_spawn_walls: true,
-                }));
+                game_rule_event.send(GameRuleChange(GameRules::WALLS));
                 next_state.set(GameState::Playing);
                 reset_event.send(ResetEvent);
             }
 
             if leaderboard_button.clicked() {
-                menu_state.leaderboard_confirmation_shown = true;
+                menu_state.browser_shown = true;
+                // scores may have come in since it was last open
+                leaderboard.forget_page();
             }
         });
+
+        ui.with_layout(egui::Layout::bottom_up(egui::Align::Min), |ui| {
+            let board = format!("Leaderboard: {}", leaderboard.label());
+            ui.label(RichText::new(board).font(FontId::proportional(15.0)));
+        });
     });
+}
 
-    if menu_state.leaderboard_confirmation_shown {
-        egui::Window::new("Confirm?")
-            .auto_sized()
-            .max_size(egui::Vec2::new(200.0, 40.0))
-            .anchor(egui::Align2::LEFT_TOP, egui::Vec2::new(301.0 - 100.0, 301.0 - 20.0))
-            .collapsible(false)
-            .open(&mut menu_state.leaderboard_confirmation_shown)
-            .show(ctx, |ui| {
-                ui.label(
-                    "Leaderboard entries may not be suitable for all users.",
-                );
-                ui.vertical_centered(|ui| {
-                    if ui.button(
-                        RichText::new("Continue")
-                            .text_style(egui::TextStyle::Heading),
-                    ).clicked() {
-                        if let Err(e) = webbrowser::open("https://berintmoffett.com/snake-leaderboard") {
-                            error!("An error occurred while trying to open the webpage: {}", e);
-                        }
+/// Pages through the whole board from the main menu. Offline it pages
+/// through the runs kept on this device instead.
+pub fn leaderboard_browser_ui(
+    mut menu_state: ResMut<MenuState>,
+    mut leaderboard: ResMut<Leaderboard>,
+    local_scores: Res<LocalLeaderboard>,
+    mut contexts: EguiContexts,
+) {
+    if !menu_state.browser_shown {
+        return;
+    }
+
+    let mut query = menu_state.browser.clone();
+    let page = if leaderboard.browse(&query) {
+        leaderboard.page(&query).cloned()
+    } else {
+        Some(Ok(query.apply(local_scores.scores(), crate::signing::now())))
+    };
+    let mut retry = false;
+
+    let menu_state = &mut *menu_state;
+    egui::Window::new("Leaderboard")
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .open(&mut menu_state.browser_shown)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for rules in GameRules::ALL {
+                    let selected = query.mode == rules.name();
+                    if ui.selectable_label(selected, rules.label()).clicked() {
+                        query.mode = rules.name().to_string();
+                        query.offset = 0;
                     }
+                }
+            });
+            ui.horizontal(|ui| {
+                for range in TimeRange::ALL {
+                    let selected = query.within == range.seconds();
+                    if ui.selectable_label(selected, range.label()).clicked() {
+                        query.within = range.seconds();
+                        query.offset = 0;
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                if ui.text_edit_singleline(&mut query.name).changed() {
+                    // names only ever hold letters and digits
+                    query.name.retain(|c| c.is_alphanumeric());
+                    query.offset = 0;
+                }
+            });
+            ui.separator();
+
+            let page = match &page {
+                None => {
+                    ui.label("loading...");
+                    return;
+                }
+                Some(Err(e)) => {
+                    ui.label(format!("failed: {}", e));
+                    retry = ui.button("Retry").clicked();
+                    return;
+                }
+                Some(Ok(page)) => page,
+            };
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    if page.entries.is_empty() {
+                        ui.label("No scores");
+                    }
+                    egui::Grid::new("browser").striped(true).show(ui, |ui| {
+                        for entry in page.entries.iter() {
+                            let details = score_details(&entry.highscore);
+                            ui.label(format!("{}.", entry.rank));
+                            ui.label(&entry.highscore.name)
+                                .on_hover_text(&details);
+                            ui.label(entry.highscore.score.to_string())
+                                .on_hover_text(&details);
+                            ui.end_row();
+                        }
+                    });
                 });
+            ui.separator();
+
+            let pages = page.total.div_ceil(query.limit).max(1);
+            ui.horizontal(|ui| {
+                let previous = egui::Button::new("Previous");
+                if ui.add_enabled(query.offset > 0, previous).clicked() {
+                    query.offset = query.offset.saturating_sub(query.limit);
+                }
+                ui.label(format!(
+                    "Page {} of {}",
+                    query.offset / query.limit + 1,
+                    pages
+                ));
+                let more = query.offset + query.limit < page.total;
+                if ui.add_enabled(more, egui::Button::new("Next")).clicked() {
+                    query.offset += query.limit;
+                }
             });
+        });
+    menu_state.browser = query;
+    if retry {
+        leaderboard.forget_page();
     }
 }
 
-pub fn playing_ui(score: Res<Score>, mut contexts: EguiContexts) {
+pub fn controls_ui(
+    mut menu_state: ResMut<MenuState>,
+    mut bindings: ResMut<KeyBindings>,
+    mut touch_settings: ResMut<TouchSettings>,
+    mut scheme: ResMut<ControlScheme>,
+    mut input_queue: ResMut<crate::snake::InputQueue>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut contexts: EguiContexts,
+) {
+    if !menu_state.controls_shown {
+        menu_state.rebinding = None;
+        return;
+    }
+
+    if let Some(action) = menu_state.rebinding {
+        let pressed = keyboard_input.get_just_pressed().next();
+        // escape backs out instead of binding
+        if pressed == Some(&KeyCode::Escape) {
+            menu_state.rebinding = None;
+            menu_state.binding_error = None;
+        } else if let Some(key) = pressed {
+            menu_state.rebinding = None;
+            match bindings.bind(action, *key) {
+                Ok(()) => {
+                    menu_state.binding_error = None;
+                    bindings.save();
+                }
+                Err(conflict) => {
+                    menu_state.binding_error = Some(format!(
+                        "{:?} is already bound to {}",
+                        key,
+                        conflict.label()
+                    ));
+                }
+            }
+        }
+    }
+
+    let menu_state = &mut *menu_state;
+    egui::Window::new("Controls")
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .open(&mut menu_state.controls_shown)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Steering");
+                for option in ControlScheme::ALL {
+                    if ui
+                        .selectable_label(*scheme == option, option.label())
+                        .clicked()
+                        && *scheme != option
+                    {
+                        *scheme = option;
+                        scheme.save();
+                    }
+                }
+            });
+            let mut depth = input_queue.0.depth();
+            ui.add(
+                egui::Slider::new(&mut depth, 1..=5)
+                    .text("Turns remembered ahead"),
+            );
+            if depth != input_queue.0.depth() {
+                input_queue.0.set_depth(depth);
+                input_queue.save();
+            }
+            ui.separator();
+
+            egui::Grid::new("bindings").show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.label());
+                    ui.horizontal(|ui| {
+                        let keys = bindings.keys(action).to_vec();
+                        for key in keys.iter().copied() {
+                            let button = ui
+                                .add_enabled(
+                                    keys.len() > 1,
+                                    egui::Button::new(format!("{:?}", key)),
+                                )
+                                .on_hover_text("Click to remove")
+                                .on_disabled_hover_text(
+                                    "The only key for this action",
+                                );
+                            if button.clicked() && bindings.unbind(action, key)
+                            {
+                                bindings.save();
+                            }
+                        }
+                        let label = if menu_state.rebinding == Some(action) {
+                            "Press a key, Escape to cancel"
+                        } else {
+                            "+"
+                        };
+                        if ui.button(label).clicked() {
+                            menu_state.rebinding = Some(action);
+                            menu_state.binding_error = None;
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+            if let Some(error) = &menu_state.binding_error {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
+            if ui.button("Reset to defaults").clicked() {
+                *bindings = KeyBindings::default();
+                bindings.save();
+                menu_state.binding_error = None;
+            }
+
+            ui.separator();
+            ui.label("Touch");
+            let mut settings = touch_settings.clone();
+            ui.add(
+                egui::Slider::new(&mut settings.swipe_distance, 10.0..=120.0)
+                    .text("Swipe distance"),
+            );
+            ui.add(
+                egui::Slider::new(&mut settings.swipe_time, 0.1..=1.5)
+                    .text("Swipe time (s)"),
+            );
+            ui.checkbox(&mut settings.show_dpad, "On-screen d-pad");
+            if settings != *touch_settings {
+                settings.save();
+                *touch_settings = settings;
+            }
+        });
+}
+
+pub fn playing_ui(
+    score: Res<Score>,
+    practice: Res<Practice>,
+    paused: Res<Paused>,
+    mut contexts: EguiContexts,
+) {
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         let score_text_raw = format!("Score: {}", score.0);
         let text =
             RichText::new(score_text_raw).font(FontId::proportional(40.0));
         ui.label(text);
+        if practice.0 {
+            let hint = RichText::new("Practice - hints on")
+                .font(FontId::proportional(15.0));
+            ui.label(hint);
+        }
+        if paused.0 {
+            ui.vertical_centered(|ui| {
+                ui.add_space(301.0 - 80.0);
+                let paused = RichText::new("Paused")
+                    .font(FontId::proportional(40.0));
+                ui.label(paused);
+            });
+        }
     });
 }
 
-pub fn game_over_ui(score: Res<Score>, mut contexts: EguiContexts) {
+pub fn game_over_ui(
+    score: Res<Score>,
+    run: Res<CurrentRun>,
+    mut contexts: EguiContexts,
+) {
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         ui.vertical_centered(|ui| {
             ui.add_space(201.0); // 301.0 - 40 * 2 - 20
             let game_over =
                 RichText::new("Game Over").font(FontId::proportional(40.0));
             ui.label(game_over);
+            if let Some(death) = run.death {
+                let cause = RichText::new(death.cause.label())
+                    .font(FontId::proportional(20.0))
+                    .color(egui::Color32::LIGHT_RED);
+                ui.label(cause);
+            }
             let score_header = RichText::new(format!("Score: {}", score.0))
                 .font(FontId::proportional(40.0));
             ui.label(score_header);
@@ -116,51 +415,284 @@ pub fn game_over_ui(score: Res<Score>, mut contexts: EguiContexts) {
     });
 }
 
+/// How long ago a Unix timestamp was, roughly
+fn ago(timestamp: u64) -> String {
+    let secs = crate::signing::now().saturating_sub(timestamp);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+/// Everything known about a score, one fact per line, for hovering over it
+/// on a board. Older records leave most of it out.
+fn score_details(highscore: &Highscore) -> String {
+    let mut lines = vec![
+        format!(
+            "Mode: {} on {}x{}",
+            highscore.rules.label(),
+            highscore.board_size,
+            highscore.board_size
+        ),
+        format!("Controls: {}", highscore.controls.label()),
+    ];
+    if let Some(timestamp) = highscore.timestamp {
+        lines.push(format!("Played: {}", ago(timestamp)));
+    }
+    if let Some(version) = &highscore.game_version {
+        lines.push(format!("Game version: {}", version));
+    }
+    if let Some(stats) = &highscore.stats {
+        let secs = stats.seconds as u32;
+        lines.push(format!("Length: {}", stats.length));
+        lines.push(format!("Time: {}:{:02}", secs / 60, secs % 60));
+        lines.push(format!("Top speed: {:.1} cells/s", stats.max_speed));
+        let ended = stats.death.map_or("Filled the board", |d| d.label());
+        lines.push(format!("Ended: {}", ended));
+    }
+    lines.join("\n")
+}
+
+/// `1st`, `2nd`, `3rd`, `4th` and so on
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
 pub fn enter_name_ui(
-    name: Res<crate::Name>,
+    mut name: ResMut<crate::Name>,
+    feedback: Res<crate::NameFeedback>,
     mut contexts: EguiContexts,
     leaderboard_earned: Res<LeaderboardEarned>,
+    submission: Res<Submission>,
+    touch_in_use: Res<TouchInUse>,
+    mut actions: ResMut<ActionState>,
 ) {
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         ui.vertical_centered(|ui| {
             let mut space = 221.0;
-            if let LeaderboardEarned::Placed(_) = *leaderboard_earned {
-                let highscore_text = RichText::new("New Highscore!")
-                    .font(FontId::proportional(40.0));
+            if let LeaderboardEarned::Placed(place) = *leaderboard_earned {
+                let highscore_text = RichText::new(format!(
+                    "New Highscore! {} place",
+                    ordinal(place)
+                ))
+                .font(FontId::proportional(40.0));
                 space -= 40.0;
                 ui.label(highscore_text);
             }
             ui.add_space(space);
 
+            let verdict = match &*submission {
+                Submission::Sending(_) => Some((
+                    RichText::new("Submitting score..."),
+                    "Skip",
+                )),
+                Submission::Rejected(reason) => Some((
+                    RichText::new(format!("Score rejected: {}", reason))
+                        .color(egui::Color32::LIGHT_RED),
+                    "Continue",
+                )),
+                _ => None,
+            };
+            if let Some((text, button)) = verdict {
+                ui.label(text.font(FontId::proportional(30.0)));
+                ui.add_space(20.0);
+                let button = egui::Button::new(
+                    RichText::new(button).font(FontId::proportional(30.0)),
+                );
+                if ui.add(button).clicked() {
+                    actions.press(Action::Confirm);
+                }
+                return;
+            }
+
             let enter_name_text =
                 RichText::new("Enter Name:").font(FontId::proportional(40.0));
             ui.label(enter_name_text);
-            let name =
+            let name_text =
                 RichText::new(&(name.0)).font(FontId::proportional(40.0));
-            ui.label(name);
+            ui.label(name_text);
+            if let Some((refused, problem)) = &feedback.0 {
+                if *refused == name.0 {
+                    let problem =
+                        RichText::new(format!("{}, try another", problem))
+                            .font(FontId::proportional(20.0))
+                            .color(egui::Color32::LIGHT_RED);
+                    ui.label(problem);
+                }
+            }
+
+            if touch_in_use.0 {
+                ui.add_space(20.0);
+                if crate::touch::on_screen_keyboard(ui, &mut name.0) {
+                    actions.press(Action::Confirm);
+                }
+            }
         });
     });
 }
 
-pub fn viewing_leaderboard_ui(mut contexts: EguiContexts) {
+pub fn viewing_leaderboard_ui(
+    leaderboard: Res<Leaderboard>,
+    local_scores: Res<LocalLeaderboard>,
+    status: Res<LeaderboardStatus>,
+    run: Res<CurrentRun>,
+    mut view_leaderboard: EventReader<ViewLeaderboardEvent>,
+    mut menu_state: ResMut<MenuState>,
+    mut acquire_highscores: EventWriter<AcquireHighscores>,
+    mut contexts: EguiContexts,
+) {
+    if view_leaderboard.read().next().is_some() {
+        menu_state.leaderboard_mode = None;
+    }
     egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
         ui.vertical_centered(|ui| {
-            ui.add_space(301.0 - 140.0);
+            ui.add_space(40.0);
             let highscore_header =
                 RichText::new("Highscores: ").font(FontId::proportional(40.0));
             ui.label(highscore_header);
-
-            let hs_arc = HIGHSCORES.get().unwrap();
-            let highscores = hs_arc.lock().unwrap();
-            for score in highscores.highscores.iter() {
-                let score_text =
-                    RichText::new(format!("{}: {}\n", score.name, score.score))
-                        .font(FontId::proportional(30.0))
-                        .line_height(Some(15.0));
-                ui.label(score_text);
+            let status_text = match &*status {
+                LeaderboardStatus::NotLoaded => "not loaded".to_string(),
+                LeaderboardStatus::Loading => "loading...".to_string(),
+                LeaderboardStatus::Loaded { at } => {
+                    format!("updated {}s ago", at.elapsed().as_secs())
+                }
+                LeaderboardStatus::Failed(e) => format!("failed: {}", e),
+            };
+            let board = RichText::new(format!(
+                "{} - {}",
+                leaderboard.label(),
+                status_text
+            ))
+            .font(FontId::proportional(15.0));
+            ui.label(board);
+            let can_retry = leaderboard.is_online()
+                && *status != LeaderboardStatus::Loading;
+            if ui
+                .add_enabled(can_retry, egui::Button::new("Retry"))
+                .clicked()
+            {
+                acquire_highscores.send(AcquireHighscores);
             }
+
+            let mut mode =
+                menu_state.leaderboard_mode.unwrap_or(run.replay.rules);
+            ui.horizontal(|ui| {
+                for rules in GameRules::ALL {
+                    ui.selectable_value(&mut mode, rules, rules.label());
+                }
+            });
+            menu_state.leaderboard_mode = Some(mode);
+
+            let merged =
+                local_scores.merged(mode.name(), leaderboard.top(mode.name()));
+            let standing = leaderboard.standing(mode.name());
+            let row = |text: String| {
+                RichText::new(text).font(FontId::proportional(22.0))
+            };
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for (i, (score, local_only)) in
+                        merged.iter().take(TOP_PLACES).enumerate()
+                    {
+                        let mut score_text = row(format!(
+                            "{}. {}: {}",
+                            i + 1,
+                            score.name,
+                            score.score
+                        ));
+                        // runs the online board hasn't seen
+                        if *local_only {
+                            score_text = score_text.italics();
+                        }
+                        let own = standing.is_some_and(|standing| {
+                            standing.rank == i + 1
+                                && standing.score == score.score
+                        });
+                        if own {
+                            score_text =
+                                score_text.color(egui::Color32::YELLOW);
+                        }
+                        ui.label(score_text)
+                            .on_hover_text(score_details(score));
+                    }
+
+                    // the run's own place, when it's further down the board
+                    let Some(standing) =
+                        standing.filter(|standing| standing.rank > TOP_PLACES)
+                    else {
+                        return;
+                    };
+                    ui.add_space(10.0);
+                    ui.label(row(format!(
+                        "Your place: {} of {}",
+                        standing.rank,
+                        standing.total + 1
+                    )));
+                    let places = standing.places();
+                    if places
+                        .first()
+                        .is_some_and(|(rank, _)| *rank > TOP_PLACES + 1)
+                    {
+                        ui.label(row("...".to_string()));
+                    }
+                    for (rank, score) in places {
+                        if rank <= TOP_PLACES {
+                            continue;
+                        }
+                        match score {
+                            Some(score) => {
+                                ui.label(row(format!(
+                                    "{}. {}: {}",
+                                    rank, score.name, score.score
+                                )))
+                                .on_hover_text(score_details(score));
+                            }
+                            None => {
+                                ui.label(
+                                    row(format!(
+                                        "{}. You: {}",
+                                        rank, standing.score
+                                    ))
+                                    .color(egui::Color32::YELLOW),
+                                );
+                            }
+                        }
+                    }
+                });
             ui.add_space(15.0);
 
+            let pending = local_scores.pending().len();
+            if pending > 0 {
+                let waiting = format!(
+                    "{} run(s) on this device waiting to be submitted",
+                    pending
+                );
+                ui.label(
+                    RichText::new(waiting).font(FontId::proportional(15.0)),
+                );
+            }
+            if let Some((refused, reason)) = local_scores.refused().last() {
+                let refused = format!(
+                    "{}'s run of {} was refused: {}",
+                    refused.name, refused.score, reason
+                );
+                ui.label(
+                    RichText::new(refused)
+                        .font(FontId::proportional(15.0))
+                        .color(egui::Color32::LIGHT_RED),
+                );
+            }
+
             let prompt = RichText::new("Press any key to continue...")
                 .font(FontId::proportional(20.0));
             ui.label(prompt);
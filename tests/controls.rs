@@ -0,0 +1,52 @@
+//! Turns pressed between ticks, queued for the snake to make in order
+
+use bevy::prelude::*;
+use rust_snake::input::{Action, ActionState, ControlScheme};
+use rust_snake::snake::{control_snake, Direction, InputQueue, SnakeHead};
+
+fn queued(scheme: ControlScheme, pressed: &[Action]) -> Vec<Direction> {
+    let mut app = App::new();
+    let mut actions = ActionState::default();
+    for action in pressed {
+        actions.press(*action);
+    }
+    app.insert_resource(actions)
+        .insert_resource(scheme)
+        .insert_resource(InputQueue::default())
+        .add_systems(Update, control_snake);
+    app.world.spawn(SnakeHead { rot: Direction::Up });
+    app.update();
+    app.world
+        .resource::<InputQueue>()
+        .0
+        .iter()
+        .copied()
+        .collect()
+}
+
+#[test]
+fn u_turn_in_one_frame_queues_both_turns() {
+    let pressed = [Action::TurnDown, Action::TurnLeft];
+    assert_eq!(
+        queued(ControlScheme::Absolute, &pressed),
+        [Direction::Left, Direction::Down]
+    );
+}
+
+#[test]
+fn relative_turns_follow_each_other() {
+    let pressed = [Action::TurnClockwise, Action::TurnCounterClockwise];
+    assert_eq!(
+        queued(ControlScheme::Relative, &pressed),
+        [Direction::Left, Direction::Up]
+    );
+}
+
+#[test]
+fn one_button_ignores_other_turns() {
+    let pressed = [Action::TurnLeft, Action::TurnClockwise];
+    assert_eq!(
+        queued(ControlScheme::OneButton, &pressed),
+        [Direction::Right]
+    );
+}
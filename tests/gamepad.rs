@@ -0,0 +1,156 @@
+//! Gamepad input, fed to the app as the events a real gamepad would send
+
+use bevy::input::gamepad::{
+    GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection,
+    GamepadConnectionEvent, GamepadEvent, GamepadInfo,
+};
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiInput};
+use rust_snake::input::{Action, ActionState, ActionsPlugin};
+use rust_snake::GameState;
+
+const PAD: Gamepad = Gamepad { id: 0 };
+
+fn app(state: GameState) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin, ActionsPlugin))
+        .insert_state(state);
+    app.world.send_event(GamepadEvent::Connection(
+        GamepadConnectionEvent::new(
+            PAD,
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Test pad".to_string(),
+            }),
+        ),
+    ));
+    app.update();
+    app
+}
+
+fn button(app: &mut App, button: GamepadButtonType, value: f32) {
+    app.world
+        .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+            PAD, button, value,
+        )));
+}
+
+fn stick(app: &mut App, x: f32, y: f32) {
+    for (axis, value) in [
+        (GamepadAxisType::LeftStickX, x),
+        (GamepadAxisType::LeftStickY, y),
+    ] {
+        app.world
+            .send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(
+                PAD, axis, value,
+            )));
+    }
+}
+
+fn actions(app: &App) -> &ActionState {
+    app.world.resource::<ActionState>()
+}
+
+#[test]
+fn dpad_turns_once_per_press() {
+    let mut app = app(GameState::Playing);
+
+    button(&mut app, GamepadButtonType::DPadUp, 1.0);
+    app.update();
+    assert!(actions(&app).just_pressed(Action::TurnUp));
+    assert!(actions(&app).any_just_pressed());
+
+    // still held
+    app.update();
+    assert!(actions(&app).pressed(Action::TurnUp));
+    assert!(!actions(&app).just_pressed(Action::TurnUp));
+
+    button(&mut app, GamepadButtonType::DPadUp, 0.0);
+    app.update();
+    assert!(!actions(&app).pressed(Action::TurnUp));
+}
+
+#[test]
+fn buttons_map_to_their_actions() {
+    let pairs = [
+        (GamepadButtonType::Start, Action::Pause),
+        (GamepadButtonType::Select, Action::Restart),
+        (GamepadButtonType::North, Action::ToggleHints),
+        (GamepadButtonType::South, Action::Confirm),
+        (GamepadButtonType::LeftTrigger, Action::TurnCounterClockwise),
+        (GamepadButtonType::RightTrigger, Action::TurnClockwise),
+    ];
+    for (pressed, action) in pairs {
+        let mut app = app(GameState::Playing);
+        button(&mut app, pressed, 1.0);
+        app.update();
+        for other in Action::ALL {
+            assert_eq!(
+                actions(&app).just_pressed(other),
+                other == action,
+                "{:?} and {:?}",
+                pressed,
+                other
+            );
+        }
+    }
+}
+
+#[test]
+fn stick_turns_when_it_changes_direction() {
+    let mut app = app(GameState::Playing);
+
+    stick(&mut app, 1.0, 0.0);
+    app.update();
+    assert!(actions(&app).just_pressed(Action::TurnRight));
+
+    // holding the stick doesn't repeat the turn
+    app.update();
+    assert!(actions(&app).pressed(Action::TurnRight));
+    assert!(!actions(&app).just_pressed(Action::TurnRight));
+
+    stick(&mut app, 0.0, -1.0);
+    app.update();
+    assert!(actions(&app).just_pressed(Action::TurnDown));
+    assert!(!actions(&app).pressed(Action::TurnRight));
+}
+
+#[test]
+fn stick_inside_the_deadzone_does_nothing() {
+    let mut app = app(GameState::Playing);
+
+    stick(&mut app, 0.3, 0.2);
+    app.update();
+    assert!(actions(&app).just_pressed_directions().next().is_none());
+}
+
+fn menu_keys(state: GameState) -> Vec<egui::Key> {
+    let mut app = app(state);
+    let input = app.world.spawn(EguiInput::default()).id();
+
+    button(&mut app, GamepadButtonType::DPadDown, 1.0);
+    button(&mut app, GamepadButtonType::South, 1.0);
+    app.update();
+
+    let events = &app.world.get::<EguiInput>(input).unwrap().0.events;
+    events
+        .iter()
+        .filter_map(|event| match event {
+            egui::Event::Key { key, .. } => Some(*key),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn dpad_navigates_menus() {
+    let keys = [egui::Key::Tab, egui::Key::Enter];
+    assert_eq!(menu_keys(GameState::MainMenu), keys);
+    assert_eq!(menu_keys(GameState::EnterName), keys);
+}
+
+#[test]
+fn dpad_leaves_menus_alone_while_playing() {
+    assert!(menu_keys(GameState::Playing).is_empty());
+    assert!(menu_keys(GameState::GameOver).is_empty());
+}
@@ -0,0 +1,249 @@
+//! A finished run going through name entry onto an in-memory board
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::{ButtonState, InputPlugin};
+use bevy::prelude::*;
+use rust_snake::game_mode::{GameRules, Practice, WallQueue};
+use rust_snake::input::ActionsPlugin;
+use rust_snake::leaderboard::{Leaderboard, LocalLeaderboard, MemoryBackend};
+use rust_snake::profile::PlayerProfile;
+use rust_snake::replay::CurrentRun;
+use rust_snake::score::*;
+use rust_snake::sim::Simulation;
+use rust_snake::snake::ExternalControl;
+use rust_snake::{
+    calc_highscores, enter_name, game_over, leaderboard, moderation, storage,
+    CalcHighscoresEvent, GameOverEvent, GameState, NameFeedback,
+    ViewLeaderboardEvent,
+};
+
+/// Eats once going straight ahead, then leaves the board
+const SEED: u64 = 13;
+
+fn highscore(name: &str, score: u32) -> Highscore {
+    Highscore::new(name, score, GameRules::CLASSIC)
+}
+
+fn app(board: &MemoryBackend) -> App {
+    // the profile and local scores are saved as the run is entered
+    let dir = std::env::temp_dir()
+        .join(format!("rust-snake-test-{}", std::process::id()));
+    std::env::set_var(storage::CONFIG_DIR_ENV, dir);
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin, ActionsPlugin))
+        .insert_state(GameState::Playing)
+        .init_state::<ScoresDownloaded>()
+        .insert_resource(Score::default())
+        .insert_resource(LeaderboardEarned::NotPlaced)
+        .insert_resource(rust_snake::Name(String::new()))
+        .insert_resource(NameFeedback::default())
+        .insert_resource(PlayerProfile::default())
+        .insert_resource(moderation::configured())
+        .insert_resource(Practice::default())
+        .insert_resource(WallQueue::default())
+        .insert_resource(Leaderboard::new(Some(Arc::new(board.clone()))))
+        .insert_resource(LocalLeaderboard::default())
+        .insert_resource(LeaderboardStatus::default())
+        .insert_resource(Submission::default())
+        .insert_resource(CurrentRun::default())
+        .add_event::<bevy::window::ReceivedCharacter>()
+        .add_event::<GameOverEvent>()
+        .add_event::<CalcHighscoresEvent>()
+        .add_event::<ViewLeaderboardEvent>()
+        .add_event::<AcquireHighscores>()
+        .add_event::<TriggerDownload>()
+        .add_event::<SendHighscores>()
+        .add_systems(
+            Update,
+            (
+                game_over.run_if(
+                    in_state(GameState::Playing)
+                        .or_else(in_state(GameState::GameOver)),
+                ),
+                calc_highscores.run_if(in_state(GameState::EnterName)),
+                enter_name.run_if(in_state(GameState::EnterName)),
+                leaderboard.run_if(in_state(GameState::ViewingLeaderboard)),
+                (download_manager, download_scores),
+                (sync_local_scores, upload_scores),
+            )
+                .chain(),
+        );
+    app
+}
+
+/// Plays the seed's run without a window and ends the game with it
+fn finish_run(app: &mut App) -> u32 {
+    let mut run = CurrentRun::new(SEED, GameRules::CLASSIC);
+    let mut sim = Simulation::new(GameRules::CLASSIC, SEED);
+    while !sim.is_over() {
+        sim.step(None);
+        run.replay.record(None);
+    }
+    let death = GameOverEvent {
+        cause: sim.death().unwrap(),
+        cell: sim.head(),
+        tick: sim.ticks(),
+    };
+    run.end(death);
+    app.insert_resource(run).insert_resource(Score(sim.score()));
+    app.world.send_event(death);
+    // the state changes on the frame after it's asked for
+    app.update();
+    app.update();
+    sim.score()
+}
+
+fn key(app: &mut App, key_code: KeyCode, logical_key: Key, pressed: bool) {
+    let state = if pressed {
+        ButtonState::Pressed
+    } else {
+        ButtonState::Released
+    };
+    app.world.send_event(KeyboardInput {
+        key_code,
+        logical_key,
+        state,
+        window: Entity::PLACEHOLDER,
+    });
+    app.update();
+}
+
+fn state(app: &App) -> GameState {
+    app.world.resource::<State<GameState>>().get().clone()
+}
+
+/// Updates until `done` holds, the backend answers on other threads
+fn wait_for(app: &mut App, done: impl Fn(&App) -> bool) {
+    let started = Instant::now();
+    while !done(app) {
+        assert!(started.elapsed() < Duration::from_secs(5), "timed out");
+        std::thread::sleep(Duration::from_millis(5));
+        app.update();
+    }
+}
+
+#[test]
+fn entered_name_reaches_the_board() {
+    let board = MemoryBackend::new(vec![
+        highscore("Ada", 5),
+        highscore("Bob", 2),
+        highscore("Cy", 0),
+    ]);
+    let mut app = app(&board);
+
+    let score = finish_run(&mut app);
+    assert_eq!(score, 1);
+    assert_eq!(state(&app), GameState::GameOver);
+
+    // any key leaves the game over screen
+    key(&mut app, KeyCode::Space, Key::Space, true);
+    key(&mut app, KeyCode::Space, Key::Space, false);
+    assert_eq!(state(&app), GameState::EnterName);
+
+    app.world.resource_mut::<rust_snake::Name>().0 = "Dee".to_string();
+    key(&mut app, KeyCode::Enter, Key::Enter, true);
+    key(&mut app, KeyCode::Enter, Key::Enter, false);
+    wait_for(&mut app, |app| state(app) == GameState::ViewingLeaderboard);
+
+    let names = board
+        .scores()
+        .iter()
+        .map(|s| (s.name.clone(), s.score))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            ("Ada".to_string(), 5),
+            ("Bob".to_string(), 2),
+            ("Dee".to_string(), 1),
+            ("Cy".to_string(), 0),
+        ]
+    );
+    let entered = &board.scores()[2];
+    assert!(entered.replay.as_ref().unwrap().verify(1).is_ok());
+    assert_eq!(
+        entered.player_id.as_ref(),
+        Some(&app.world.resource::<PlayerProfile>().id)
+    );
+
+    wait_for(&mut app, |app| {
+        matches!(
+            app.world.resource::<LeaderboardEarned>(),
+            LeaderboardEarned::Placed(3)
+        )
+    });
+    assert!(app
+        .world
+        .resource::<LocalLeaderboard>()
+        .pending()
+        .is_empty());
+    assert_eq!(app.world.resource::<LocalLeaderboard>().scores().len(), 1);
+
+    let dir = std::env::var_os(storage::CONFIG_DIR_ENV).unwrap();
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn skipped_name_entry_is_placed_before_it_is_submitted() {
+    let board = MemoryBackend::new(vec![
+        highscore("Ada", 5),
+        highscore("Bob", 1),
+        highscore("Cy", 0),
+    ]);
+    let mut app = app(&board);
+    let profile = PlayerProfile {
+        name: "Dee".to_string(),
+        skip_name_entry: true,
+        ..Default::default()
+    };
+    app.insert_resource(profile);
+    app.world.resource_mut::<rust_snake::Name>().0 = "Dee".to_string();
+
+    finish_run(&mut app);
+    key(&mut app, KeyCode::Space, Key::Space, true);
+    key(&mut app, KeyCode::Space, Key::Space, false);
+    wait_for(&mut app, |app| state(app) == GameState::ViewingLeaderboard);
+
+    // ties place below, so counting itself would have put the run fourth
+    let standing = app
+        .world
+        .resource::<Leaderboard>()
+        .standing("classic")
+        .cloned()
+        .unwrap();
+    assert_eq!((standing.rank, standing.total), (3, 3));
+    assert_eq!(board.scores()[2].name, "Dee");
+    assert_eq!(board.scores().len(), 4);
+}
+
+#[test]
+fn bot_runs_never_reach_the_board() {
+    let board = MemoryBackend::new(vec![highscore("Ada", 5)]);
+    let mut app = app(&board);
+    // nothing would stop a ranked run going straight onto the board
+    let profile = PlayerProfile {
+        name: "Dee".to_string(),
+        skip_name_entry: true,
+        ..Default::default()
+    };
+    app.insert_resource(profile)
+        .insert_resource(ExternalControl);
+    app.world.resource_mut::<rust_snake::Name>().0 = "Dee".to_string();
+
+    finish_run(&mut app);
+    key(&mut app, KeyCode::Space, Key::Space, true);
+    key(&mut app, KeyCode::Space, Key::Space, false);
+    assert_eq!(state(&app), GameState::ReadyToReset);
+    for _ in 0..10 {
+        assert!(app.world.resource::<Events<SendHighscores>>().is_empty());
+        std::thread::sleep(Duration::from_millis(5));
+        app.update();
+    }
+
+    assert_eq!(board.scores().len(), 1);
+    assert!(app.world.resource::<LocalLeaderboard>().scores().is_empty());
+}
@@ -0,0 +1,162 @@
+//! Restarting in the middle of a Walls game starts the next run on a clean
+//! board, so its replay plays back the way it was played
+
+use std::time::Duration;
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::{ButtonState, InputPlugin};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use rust_snake::ai::{Pathfinder, Strategy};
+use rust_snake::cheats::{quick_reset, ScoreBlocker};
+use rust_snake::food::food_spawner;
+use rust_snake::game_mode::{
+    enqueue_walls, try_spawn_walls, GameRules, Practice, Wall, WallQueue,
+};
+use rust_snake::input::ActionsPlugin;
+use rust_snake::replay::CurrentRun;
+use rust_snake::score::Score;
+use rust_snake::sim::Simulation;
+use rust_snake::snake::*;
+use rust_snake::{
+    game_over, reset_game, CalcHighscoresEvent, GameOverEvent, GameState,
+    ResetEvent, TickTimer, TICK_RATE,
+};
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin, ActionsPlugin))
+        // one tick of the slowest speed per frame, so every frame moves
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Duration::from_secs_f32(1. / TICK_RATE),
+        ))
+        .insert_state(GameState::Playing)
+        .insert_resource(SnakeSegments::default())
+        .insert_resource(LastTailPosition::default())
+        .insert_resource(TickTimer(Timer::from_seconds(
+            1. / TICK_RATE,
+            TimerMode::Repeating,
+        )))
+        .insert_resource(NextDirection::default())
+        .insert_resource(Score::default())
+        .insert_resource(InputQueue::default())
+        .insert_resource(TickAccum(TICK_RATE))
+        .insert_resource(ScoreBlocker(0))
+        .insert_resource(GameRules::WALLS)
+        .insert_resource(WallQueue::default())
+        .insert_resource(Practice::default())
+        .insert_resource(CurrentRun::default())
+        .add_event::<EatEvent>()
+        .add_event::<GameOverEvent>()
+        .add_event::<CalcHighscoresEvent>()
+        .add_event::<ResetEvent>()
+        .add_systems(
+            Update,
+            (
+                (
+                    food_spawner,
+                    update_snake,
+                    snake_eating,
+                    enqueue_walls,
+                    snake_growth,
+                    try_spawn_walls,
+                    quick_reset,
+                    game_over,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+                reset_game,
+            )
+                .chain(),
+        );
+    app
+}
+
+fn run(app: &App) -> &CurrentRun {
+    app.world.resource::<CurrentRun>()
+}
+
+fn score(app: &App) -> u32 {
+    app.world.resource::<Score>().0
+}
+
+fn walls(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<Wall>>()
+        .iter(&app.world)
+        .count()
+}
+
+/// Queues the turn a pathfinder would make on the replay's board
+fn steer(app: &mut App) {
+    let sim = run(app).replay.simulate().unwrap();
+    if let Some(dir) = Pathfinder.next_direction(&sim) {
+        app.world
+            .resource_mut::<InputQueue>()
+            .0
+            .push(dir, sim.heading());
+    }
+}
+
+/// Moves the snake one tick, returning the replay's board after it
+fn tick(app: &mut App, steered: bool) -> Simulation {
+    if steered {
+        steer(app);
+    }
+    let ticks = run(app).replay.ticks;
+    while run(app).replay.ticks == ticks {
+        assert!(run(app).death.is_none(), "the run is over");
+        app.update();
+    }
+    run(app).replay.simulate().unwrap()
+}
+
+fn press(app: &mut App, key_code: KeyCode, logical_key: Key) {
+    for state in [ButtonState::Pressed, ButtonState::Released] {
+        app.world.send_event(KeyboardInput {
+            key_code,
+            logical_key: logical_key.clone(),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
+    }
+}
+
+#[test]
+fn restart_clears_walls_and_their_queue() {
+    let mut app = app();
+    app.world.send_event(ResetEvent);
+    app.update();
+
+    // play until food is eaten with a wall already on the board, so
+    // another is queued under the head
+    loop {
+        let eaten = score(&app);
+        let sim = tick(&mut app, true);
+        assert_eq!(sim.score(), score(&app));
+        if score(&app) > eaten && walls(&mut app) > 0 {
+            break;
+        }
+    }
+
+    // Restart's default key, pressed with a safe turn queued
+    steer(&mut app);
+    press(&mut app, KeyCode::Backslash, Key::Character("\\".into()));
+    assert_eq!(score(&app), 0);
+    assert_eq!(walls(&mut app), 0);
+
+    // the live board keeps matching the replay's through the next run
+    while score(&app) < 2 {
+        let sim = tick(&mut app, true);
+        assert_eq!(walls(&mut app), sim.walls().len());
+    }
+    loop {
+        let sim = tick(&mut app, false);
+        if run(&app).death.is_some() {
+            break;
+        }
+        assert_eq!(walls(&mut app), sim.walls().len());
+    }
+    assert!(run(&app).replay.verify(score(&app)).is_ok());
+}